@@ -0,0 +1,50 @@
+//! Compares evaluating an expression in a new session, which starts a worker with a copy of the
+//! prelude first, with evaluating it in a session that already has one.
+
+use std::time::Duration;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use liege_bot::math::{EvaluationLimits, MathSessions};
+use serenity::all::UserId;
+
+const EXPRESSION: &str = "3 km / 20 min -> km/h";
+
+fn bench_evaluation(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    // Every iteration adds to the session, which would otherwise become too large
+    let sessions = MathSessions::new(
+        1,
+        Duration::from_secs(60),
+        EvaluationLimits {
+            max_session_size: usize::MAX,
+            ..Default::default()
+        },
+        env!("CARGO_BIN_EXE_liege-bot"),
+    );
+    let user_id = UserId::new(1);
+
+    let evaluate = || {
+        runtime
+            .block_on(sessions.evaluate(user_id, EXPRESSION, false))
+            .unwrap()
+    };
+
+    let mut group = c.benchmark_group("evaluate");
+
+    group.sample_size(10);
+
+    group.bench_function("new session", |b| {
+        b.iter(|| {
+            sessions.reset(user_id);
+            evaluate()
+        })
+    });
+
+    group.bench_function("existing session", |b| b.iter(evaluate));
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_evaluation);
+criterion_main!(benches);
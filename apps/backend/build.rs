@@ -0,0 +1,4 @@
+// Embedded migrations are only picked up on rebuild, see `sqlx::migrate!`
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -0,0 +1,18 @@
+CREATE TABLE wallets (
+    user_id INTEGER PRIMARY KEY NOT NULL,
+    balance INTEGER NOT NULL DEFAULT 0 CHECK (balance >= 0),
+    last_daily_at TEXT
+);
+
+-- Every balance change is recorded so disputes can be investigated
+CREATE TABLE ledger (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    user_id INTEGER NOT NULL,
+    amount INTEGER NOT NULL,
+    balance INTEGER NOT NULL,
+    reason TEXT NOT NULL,
+    counterparty_id INTEGER,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX ledger_user_id ON ledger (user_id);
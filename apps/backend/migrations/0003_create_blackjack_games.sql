@@ -0,0 +1,8 @@
+-- The bet of a game is held in escrow (already deducted from the wallet) until it is finished
+CREATE TABLE blackjack_games (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    user_id INTEGER NOT NULL,
+    state TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    finished_at TEXT
+);
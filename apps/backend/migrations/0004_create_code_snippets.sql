@@ -0,0 +1,12 @@
+-- Source of code executions from Discord, referenced by the buttons on their results since
+-- the code does not fit into a custom id
+CREATE TABLE code_snippets (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    user_id INTEGER NOT NULL,
+    language TEXT NOT NULL,
+    version TEXT NOT NULL,
+    code TEXT NOT NULL,
+    stdin TEXT,
+    args TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
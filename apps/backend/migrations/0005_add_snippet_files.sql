@@ -0,0 +1,2 @@
+-- Additional files of multi-file snippets, stored as a JSON array next to the main code
+ALTER TABLE code_snippets ADD COLUMN files TEXT NOT NULL DEFAULT '[]';
@@ -0,0 +1,13 @@
+-- numbat modules saved with `/math module save` and imported by name, e.g. `use team::constants`.
+-- Modules belong to a user, or to a guild to share them with all of its members
+CREATE TABLE math_modules (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    scope TEXT NOT NULL,
+    owner_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    code TEXT NOT NULL,
+    author_id INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    UNIQUE (scope, owner_id, name)
+);
@@ -0,0 +1,23 @@
+-- Conversations of `/ai text`, continued with `/ai reply` or the button below each answer
+CREATE TABLE ai_conversations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    channel_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    -- Summary of the messages that were removed to keep the history short
+    summary TEXT,
+    tokens_used INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE INDEX ai_conversations_channel_id ON ai_conversations (channel_id);
+
+CREATE TABLE ai_messages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    conversation_id INTEGER NOT NULL REFERENCES ai_conversations (id) ON DELETE CASCADE,
+    role TEXT NOT NULL,
+    content TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX ai_messages_conversation_id ON ai_messages (conversation_id);
@@ -0,0 +1,2 @@
+-- Name of the main file of snippets that were run from an uploaded file
+ALTER TABLE code_snippets ADD COLUMN name TEXT;
@@ -0,0 +1,2 @@
+-- Snippets are purged by age, see `SnippetRepository::delete_snippets_before`
+CREATE INDEX code_snippets_created_at ON code_snippets (created_at);
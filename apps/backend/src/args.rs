@@ -13,10 +13,38 @@ impl Cli {
 }
 
 #[derive(clap::Subcommand, Clone)]
+#[allow(clippy::enum_variant_names)]
 pub enum Command {
+    /// Registers the commands, skipping the request if nothing changed
     RegisterCommands {
         #[arg(long, short)]
         guild_id: Option<String>,
+
+        /// Only print the difference to the registered commands
+        #[arg(long)]
+        dry_run: bool,
     },
+    /// Lists the commands currently registered on Discord
+    ListCommands {
+        #[arg(long, short)]
+        guild_id: Option<String>,
+    },
+    /// Deletes a single registered command by name
+    DeleteCommand {
+        name: String,
+
+        #[arg(long, short)]
+        guild_id: Option<String>,
+    },
+    /// Applies pending database migrations
+    Migrate,
+    /// Starts the webserver (default)
     Run,
+    /// Evaluates math for the webserver, which starts one worker per session
+    #[command(hide = true)]
+    MathWorker {
+        /// Bytes the worker may allocate after loading the prelude
+        #[arg(long)]
+        max_memory: usize,
+    },
 }
@@ -7,12 +7,8 @@ use axum::{
     http::StatusCode,
     response::{IntoResponse, Response, Sse, sse},
 };
-use futures::{Stream, StreamExt};
+use futures::{Stream, StreamExt, stream};
 use reqwest::Url;
-use reqwest_eventsource::{
-    RequestBuilderExt,
-    retry::{self},
-};
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -22,10 +18,11 @@ use crate::{
     models::{
         api::ai::{
             GenerateImageRequest, GenerateImageResponse, GenerateTextMessage,
-            GenerateTextMessageRole, GenerateTextRequest, GenerateTextStreamResponse,
+            GenerateTextMessageRole,
         },
         auth::Claims,
     },
+    services::ai::AiClient,
 };
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -109,7 +106,7 @@ pub async fn post(
                 messages
             };
 
-            match generate_text(&state.http_client, messages).await {
+            match generate_text(&state.ai, messages).await {
                 Ok(sse) => sse.into_response(),
                 Err(_) => {
                     (StatusCode::INTERNAL_SERVER_ERROR, "Failed to generate text").into_response()
@@ -120,63 +117,37 @@ pub async fn post(
 }
 
 async fn generate_text(
-    http: &reqwest::Client,
+    ai: &AiClient,
     messages: Vec<GenerateTextMessage>,
 ) -> Result<Sse<impl Stream<Item = Result<sse::Event, Infallible>> + use<>>, Error> {
-    let mut event_source = http
-        .post("https://ai.nigga.church/v2/generate/text")
-        .header("Authorization", &ENV.ai_token)
-        .json(
-            &GenerateTextRequest::new()
-                .model("llama-3-8b-instruct")
-                .messages(messages)
-                .stream(true),
-        )
-        .eventsource()?;
-
-    event_source.set_retry_policy(Box::new(retry::Never));
+    let chunks = ai.stream_with_model(&ENV.ai_chat_model, messages)?;
 
-    let event_stream = event_source
-        .map(|event| -> Option<sse::Event> {
-            match event {
-                Ok(reqwest_eventsource::Event::Open) => {
-                    tracing::debug!("opening sse");
-                    None
-                }
-                Ok(reqwest_eventsource::Event::Message(message)) => {
-                    if message.data == "[DONE]" {
-                        return Some(
-                            sse::Event::default().data(serde_json::to_string(&AiEvent::Done).ok()?),
-                        );
-                    }
-
-                    let data =
-                        serde_json::from_str::<GenerateTextStreamResponse>(&message.data).ok()?;
-
-                    Some(
-                        sse::Event::default()
-                            .data(serde_json::to_string(&AiEvent::Response(data.response)).ok()?),
-                    )
-                }
+    let event_stream = chunks
+        .filter_map(|chunk| async move {
+            match chunk {
+                Ok(response) => Some(AiEvent::Response(response)),
                 Err(error) => {
                     tracing::error!(%error, "failed to get next event of sse stream");
                     None
                 }
             }
         })
-        .filter_map(|event| async move { event })
-        .map(|event| Ok(event));
+        .chain(stream::once(async { AiEvent::Done }))
+        .filter_map(|event| async move {
+            Some(sse::Event::default().data(serde_json::to_string(&event).ok()?))
+        })
+        .map(Ok);
 
     Ok(Sse::new(event_stream))
 }
 
 async fn generate_image(http: &reqwest::Client, prompt: String) -> Result<Url, Error> {
     let response = http
-        .post("https://ai.nigga.church/v3/generate/image")
+        .post(format!("{}/v3/generate/image", ENV.ai_api_url))
         .header("Authorization", &ENV.ai_token)
         .json(
             &GenerateImageRequest::new()
-                .source("flux-1-schnell")
+                .source(&ENV.ai_image_model)
                 .prompt(prompt),
         )
         .send()
@@ -10,15 +10,26 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     AppState,
-    env::ENV,
-    error::Error,
-    models::{api, auth::Claims},
+    models::{
+        api::code::{ExecuteFile, ExecuteRequest},
+        auth::Claims,
+    },
+    services::code::{CodeExecutor, LimitPolicy, RequestedLimits},
 };
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CodeRequest {
     pub language: String,
-    pub code: String,
+    pub code: Option<String>,
+    /// Files sent after `code`, the first file is run if `code` is missing.
+    #[serde(default)]
+    pub files: Vec<ExecuteFile>,
+    pub version: Option<String>,
+    pub stdin: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(flatten)]
+    pub limits: RequestedLimits,
 }
 
 pub async fn post(
@@ -26,31 +37,39 @@ pub async fn post(
     State(state): State<Arc<AppState>>,
     Json(body): Json<CodeRequest>,
 ) -> Response {
-    match execute_code(&state.http_client, &body.language, &body.code).await {
+    if body.code.is_none() && body.files.is_empty() {
+        return (StatusCode::BAD_REQUEST, "code or files are required").into_response();
+    }
+
+    let mut request = ExecuteRequest::new()
+        .language(body.language)
+        .version(body.version.as_deref().unwrap_or("*"))
+        .args(body.args);
+
+    if let Some(code) = body.code {
+        request = request.add_file(ExecuteFile::new().content(code));
+    }
+
+    request = body
+        .files
+        .into_iter()
+        .fold(request, ExecuteRequest::add_file);
+
+    if let Some(stdin) = body.stdin {
+        request = request.stdin(stdin);
+    }
+
+    let request = LimitPolicy::API.apply(request, &body.limits);
+
+    match state.code_executor.execute(&request).await {
         Ok(response) => (StatusCode::OK, Json(response)).into_response(),
         Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "failed to execute code").into_response(),
     }
 }
 
-async fn execute_code(
-    http: &reqwest::Client,
-    language: &str,
-    code: &str,
-) -> Result<api::code::ExecuteResponse, Error> {
-    let response = http
-        .post("https://v2-api.nigga.church/code/execute")
-        .header("Authorization", &ENV.code_token)
-        .json(
-            &api::code::ExecuteRequest::new()
-                .language(language)
-                .version("*")
-                .add_file(api::code::ExecuteFile::new().content(code)),
-        )
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<api::code::ExecuteResponse>()
-        .await?;
-
-    Ok(response)
+pub async fn languages(_claims: Claims, State(state): State<Arc<AppState>>) -> Response {
+    match state.languages.ensure_loaded(&state.code_executor).await {
+        Ok(()) => (StatusCode::OK, Json(state.languages.languages())).into_response(),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "failed to get languages").into_response(),
+    }
 }
@@ -13,10 +13,10 @@ use serenity::all::{
     Interaction,
 };
 
-use crate::{AppState, error::Error, handlers};
+use crate::{AppState, error::Error, handlers, models::database::users::UserRepository};
 
 pub async fn post(headers: HeaderMap, State(state): State<Arc<AppState>>, body: Bytes) -> Response {
-    if let Err(_) = verify_signature(&state, &headers, &body) {
+    if verify_signature(&state, &headers, &body).is_err() {
         return (StatusCode::UNAUTHORIZED, "failed to verify signature").into_response();
     }
 
@@ -29,6 +29,8 @@ pub async fn post(headers: HeaderMap, State(state): State<Arc<AppState>>, body:
         return (StatusCode::OK, Json(CreateInteractionResponse::Pong)).into_response();
     }
 
+    touch_user(&interaction, &state);
+
     tokio::spawn(async move {
         if let Err(error) = handle_interaction(interaction.clone(), state.clone()).await {
             handle_interaction_error(error, interaction, state).await;
@@ -45,15 +47,16 @@ async fn handle_interaction_error(error: Error, interaction: Interaction, state:
 
     match interaction {
         Interaction::Command(interaction) => {
-            if let Err(_) = interaction
+            let response = interaction
                 .create_response(
                     &state.serenity_http,
                     CreateInteractionResponse::Message(
                         CreateInteractionResponseMessage::new().content(&error_message),
                     ),
                 )
-                .await
-            {
+                .await;
+
+            if response.is_err() {
                 interaction
                     .edit_response(
                         &state.serenity_http,
@@ -64,15 +67,16 @@ async fn handle_interaction_error(error: Error, interaction: Interaction, state:
             };
         }
         Interaction::Component(interaction) => {
-            if let Err(_) = interaction
+            let response = interaction
                 .create_response(
                     &state.serenity_http,
                     CreateInteractionResponse::Message(
                         CreateInteractionResponseMessage::new().content(&error_message),
                     ),
                 )
-                .await
-            {
+                .await;
+
+            if response.is_err() {
                 interaction
                     .edit_response(
                         &state.serenity_http,
@@ -83,15 +87,16 @@ async fn handle_interaction_error(error: Error, interaction: Interaction, state:
             };
         }
         Interaction::Modal(interaction) => {
-            if let Err(_) = interaction
+            let response = interaction
                 .create_response(
                     &state.serenity_http,
                     CreateInteractionResponse::Message(
                         CreateInteractionResponseMessage::new().content(&error_message),
                     ),
                 )
-                .await
-            {
+                .await;
+
+            if response.is_err() {
                 interaction
                     .edit_response(
                         &state.serenity_http,
@@ -111,6 +116,12 @@ async fn handle_interaction(interaction: Interaction, state: Arc<AppState>) -> R
         Interaction::Command(interaction) => {
             handlers::commands::handle_interaction(interaction, state).await?;
         }
+        Interaction::Autocomplete(interaction) => {
+            handlers::commands::handle_autocomplete(interaction, state).await?;
+        }
+        Interaction::Component(interaction) => {
+            handlers::components::handle_interaction(interaction, state).await?;
+        }
         Interaction::Modal(interaction) => {
             handlers::modals::handle_interaction(interaction, state).await?;
         }
@@ -120,6 +131,24 @@ async fn handle_interaction(interaction: Interaction, state: Arc<AppState>) -> R
     Ok(())
 }
 
+/// Records that the user of `interaction` was seen, without delaying the response to it.
+fn touch_user(interaction: &Interaction, state: &Arc<AppState>) {
+    let user_id = match interaction {
+        Interaction::Command(interaction) => interaction.user.id,
+        Interaction::Component(interaction) => interaction.user.id,
+        Interaction::Modal(interaction) => interaction.user.id,
+        _ => return,
+    };
+
+    let state = state.clone();
+
+    tokio::spawn(async move {
+        if let Err(error) = state.database.touch_user(user_id).await {
+            tracing::warn!(%error, %user_id, "failed to touch user");
+        }
+    });
+}
+
 fn verify_signature(state: &Arc<AppState>, headers: &HeaderMap, body: &Bytes) -> Result<(), Error> {
     let signature = headers
         .get("X-Signature-Ed25519")
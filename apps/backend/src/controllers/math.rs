@@ -1,7 +1,18 @@
-use axum::Json;
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
 use serde::{Deserialize, Serialize};
 
-use crate::{math, models::auth::Claims};
+use crate::{
+    AppState,
+    math::Modules,
+    models::{auth::Claims, database::math_modules::MathModuleRepository},
+};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MathRequest {
@@ -14,10 +25,26 @@ pub struct MathResponse {
     pub output: String,
 }
 
-pub async fn post(_claims: Claims, Json(body): Json<MathRequest>) -> Json<MathResponse> {
-    let result = math::evaluate_html(&body.input);
+pub async fn post(
+    claims: Claims,
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<MathRequest>,
+) -> Response {
+    // There is no guild on the web, so only the modules of the user can be imported
+    let modules: Modules = match state.database.importable_modules(claims.sub, None).await {
+        Ok(modules) => modules.into_iter().collect(),
+        Err(_) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, "failed to load modules").into_response();
+        }
+    };
+
+    let result = state
+        .math_sessions
+        .with_modules(modules)
+        .evaluate(claims.sub, &body.input, true)
+        .await;
     let success = result.is_ok();
-    let output = result.unwrap_or_else(|o| o);
+    let output = result.unwrap_or_else(|error| error.to_string());
 
-    Json(MathResponse { success, output })
+    Json(MathResponse { success, output }).into_response()
 }
@@ -59,7 +59,7 @@ async fn get_discord_oauth_token(
     form.insert("client_id", &ENV.discord_app_id);
     form.insert("client_secret", &ENV.discord_client_secret);
     form.insert("grant_type", "authorization_code");
-    form.insert("code", &code);
+    form.insert("code", code);
 
     let response = state
         .http_client
@@ -1,28 +1,57 @@
 use std::sync::LazyLock;
 
 fn required_var(name: &str) -> String {
-    std::env::var(name).expect(&format!("Missing environment variable `{name}`"))
+    std::env::var(name).unwrap_or_else(|_| panic!("Missing environment variable `{name}`"))
+}
+
+fn optional_var(name: &str, default: &str) -> String {
+    std::env::var(name).unwrap_or_else(|_| default.to_string())
+}
+
+fn url_var(name: &str, default: &str) -> String {
+    optional_var(name, default)
+        .trim_end_matches('/')
+        .to_string()
 }
 
 #[derive(Debug)]
 pub struct Env {
+    pub ai_api_url: String,
+    pub ai_chat_model: String,
+    pub ai_image_model: String,
+    pub ai_text_model: String,
     pub ai_token: String,
+    pub code_api_url: String,
     pub code_token: String,
+    pub database_url: String,
     pub discord_app_id: String,
     pub discord_client_secret: String,
     pub discord_token: String,
     pub discord_public_key: String,
+    pub exchange_rates_cache: String,
+    pub exchange_rates_url: String,
     pub jwt_secret: String,
 }
 
 pub static ENV: LazyLock<Env> = LazyLock::new(|| {
     let env = Env {
+        ai_api_url: url_var("AI_API_URL", "https://ai.nigga.church"),
+        ai_chat_model: optional_var("AI_CHAT_MODEL", "llama-3-8b-instruct"),
+        ai_image_model: optional_var("AI_IMAGE_MODEL", "flux-1-schnell"),
+        ai_text_model: optional_var("AI_TEXT_MODEL", "perplexity-sonar-pro"),
         ai_token: required_var("AI_TOKEN"),
+        code_api_url: url_var("CODE_API_URL", "https://v2-api.nigga.church/code"),
         code_token: required_var("CODE_TOKEN"),
+        database_url: optional_var("DATABASE_URL", "sqlite://data/liege.db"),
         discord_app_id: required_var("DISCORD_APP_ID"),
         discord_client_secret: required_var("DISCORD_CLIENT_SECRET"),
         discord_public_key: required_var("DISCORD_PUBLIC_KEY"),
         discord_token: required_var("DISCORD_TOKEN"),
+        exchange_rates_cache: optional_var("EXCHANGE_RATES_CACHE", "data/exchange-rates.xml"),
+        exchange_rates_url: url_var(
+            "EXCHANGE_RATES_URL",
+            "https://www.ecb.europa.eu/stats/eurofxref/eurofxref-daily.xml",
+        ),
         jwt_secret: required_var("JWT_SECRET"),
     };
 
@@ -0,0 +1,281 @@
+use std::fmt::Display;
+
+use rand::{Rng, seq::SliceRandom};
+use serde::{Deserialize, Serialize};
+
+const BLACKJACK: u8 = 21;
+const DEALER_STANDS_ON: u8 = 17;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Suit {
+    Clubs,
+    Diamonds,
+    Hearts,
+    Spades,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Card {
+    /// 1 is an ace, 11 to 13 are jack, queen and king.
+    pub rank: u8,
+    pub suit: Suit,
+}
+
+impl Card {
+    pub fn new(rank: u8, suit: Suit) -> Self {
+        Self { rank, suit }
+    }
+
+    fn value(&self) -> u8 {
+        self.rank.min(10)
+    }
+}
+
+impl Display for Card {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rank = match self.rank {
+            1 => String::from("A"),
+            11 => String::from("J"),
+            12 => String::from("Q"),
+            13 => String::from("K"),
+            rank => rank.to_string(),
+        };
+
+        let suit = match self.suit {
+            Suit::Clubs => "♣",
+            Suit::Diamonds => "♦",
+            Suit::Hearts => "♥",
+            Suit::Spades => "♠",
+        };
+
+        write!(f, "{rank}{suit}")
+    }
+}
+
+/// Value of a hand, counting one ace as 11 if that does not bust the hand.
+pub fn hand_value(cards: &[Card]) -> u8 {
+    let value: u8 = cards.iter().map(Card::value).sum();
+    let has_ace = cards.iter().any(|card| card.rank == 1);
+
+    if has_ace && value + 10 <= BLACKJACK {
+        value + 10
+    } else {
+        value
+    }
+}
+
+fn is_blackjack(cards: &[Card]) -> bool {
+    cards.len() == 2 && hand_value(cards) == BLACKJACK
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Outcome {
+    Blackjack,
+    Win,
+    Push,
+    Lose,
+}
+
+impl Outcome {
+    /// Returns the amount paid out to the player, which includes the bet.
+    pub fn payout(&self, bet: i64) -> i64 {
+        match self {
+            Self::Blackjack => bet * 5 / 2,
+            Self::Win => bet * 2,
+            Self::Push => bet,
+            Self::Lose => 0,
+        }
+    }
+}
+
+/// State of a single blackjack game. The deck is shuffled once when the game starts, so the state
+/// can be stored between turns without keeping the random number generator around.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Blackjack {
+    pub bet: i64,
+    pub player: Vec<Card>,
+    pub dealer: Vec<Card>,
+    deck: Vec<Card>,
+    outcome: Option<Outcome>,
+}
+
+impl Blackjack {
+    pub fn new(bet: i64, rng: &mut impl Rng) -> Self {
+        let mut deck: Vec<_> = [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades]
+            .into_iter()
+            .flat_map(|suit| (1..=13).map(move |rank| Card::new(rank, suit)))
+            .collect();
+        deck.shuffle(rng);
+
+        Self::from_deck(bet, deck)
+    }
+
+    /// Starts a game that draws from the end of `deck`.
+    pub fn from_deck(bet: i64, mut deck: Vec<Card>) -> Self {
+        let mut draw = || deck.pop().expect("deck has enough cards to deal");
+        let player = vec![draw(), draw()];
+        let dealer = vec![draw(), draw()];
+
+        let mut game = Self {
+            bet,
+            player,
+            dealer,
+            deck,
+            outcome: None,
+        };
+
+        if is_blackjack(&game.player) || is_blackjack(&game.dealer) {
+            game.finish();
+        }
+
+        game
+    }
+
+    /// The outcome once the game is over.
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.outcome.is_some()
+    }
+
+    /// Draws another card for the player, ending the game if the hand busts.
+    pub fn hit(&mut self) {
+        if self.is_finished() {
+            return;
+        }
+
+        if let Some(card) = self.deck.pop() {
+            self.player.push(card);
+        }
+
+        if hand_value(&self.player) >= BLACKJACK {
+            self.stand();
+        }
+    }
+
+    /// Ends the turn of the player and lets the dealer draw.
+    pub fn stand(&mut self) {
+        if self.is_finished() {
+            return;
+        }
+
+        if hand_value(&self.player) <= BLACKJACK {
+            while hand_value(&self.dealer) < DEALER_STANDS_ON {
+                let Some(card) = self.deck.pop() else { break };
+                self.dealer.push(card);
+            }
+        }
+
+        self.finish();
+    }
+
+    fn finish(&mut self) {
+        let player = hand_value(&self.player);
+        let dealer = hand_value(&self.dealer);
+
+        let outcome = match (is_blackjack(&self.player), is_blackjack(&self.dealer)) {
+            (true, true) => Outcome::Push,
+            (true, false) => Outcome::Blackjack,
+            (false, true) => Outcome::Lose,
+            _ if player > BLACKJACK => Outcome::Lose,
+            _ if dealer > BLACKJACK || player > dealer => Outcome::Win,
+            _ if player == dealer => Outcome::Push,
+            _ => Outcome::Lose,
+        };
+
+        self.outcome = Some(outcome);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    /// Builds a deck which deals `cards` in order.
+    fn deck(cards: &[u8]) -> Vec<Card> {
+        cards
+            .iter()
+            .rev()
+            .map(|&rank| Card::new(rank, Suit::Spades))
+            .collect()
+    }
+
+    #[test]
+    fn ace_values() {
+        let cards = deck(&[1, 13]);
+        assert_eq!(hand_value(&cards), 21);
+
+        let cards = deck(&[1, 1, 9]);
+        assert_eq!(hand_value(&cards), 21);
+
+        let cards = deck(&[1, 9, 5]);
+        assert_eq!(hand_value(&cards), 15);
+    }
+
+    #[test]
+    fn natural_blackjack_pays_three_to_two() {
+        // Player gets A + K, dealer gets 9 + 9
+        let game = Blackjack::from_deck(10, deck(&[1, 13, 9, 9]));
+
+        assert_eq!(game.outcome(), Some(Outcome::Blackjack));
+        assert_eq!(Outcome::Blackjack.payout(10), 25);
+    }
+
+    #[test]
+    fn player_busts() {
+        // Player gets 10 + 6, dealer gets 10 + 7, player draws a king
+        let mut game = Blackjack::from_deck(10, deck(&[10, 6, 10, 7, 13]));
+        assert!(!game.is_finished());
+
+        game.hit();
+        assert_eq!(game.outcome(), Some(Outcome::Lose));
+    }
+
+    #[test]
+    fn dealer_draws_to_seventeen() {
+        // Player gets 10 + 8, dealer gets 10 + 2 and draws 3 and 5
+        let mut game = Blackjack::from_deck(10, deck(&[10, 8, 10, 2, 3, 5]));
+
+        game.stand();
+        assert_eq!(hand_value(&game.dealer), 20);
+        assert_eq!(game.outcome(), Some(Outcome::Lose));
+    }
+
+    #[test]
+    fn dealer_busts() {
+        // Player gets 10 + 8, dealer gets 10 + 6 and draws a queen
+        let mut game = Blackjack::from_deck(10, deck(&[10, 8, 10, 6, 12]));
+
+        game.stand();
+        assert_eq!(game.outcome(), Some(Outcome::Win));
+        assert_eq!(Outcome::Win.payout(10), 20);
+    }
+
+    #[test]
+    fn push_on_equal_hands() {
+        let mut game = Blackjack::from_deck(10, deck(&[10, 8, 10, 8]));
+
+        game.stand();
+        assert_eq!(game.outcome(), Some(Outcome::Push));
+    }
+
+    #[test]
+    fn seeded_games_are_deterministic() {
+        let first = Blackjack::new(10, &mut StdRng::seed_from_u64(1));
+        let second = Blackjack::new(10, &mut StdRng::seed_from_u64(1));
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn state_roundtrip() {
+        let game = Blackjack::new(10, &mut StdRng::seed_from_u64(1));
+        let json = serde_json::to_string(&game).unwrap();
+
+        assert_eq!(serde_json::from_str::<Blackjack>(&json).unwrap(), game);
+    }
+}
@@ -0,0 +1,64 @@
+use std::fmt::Display;
+
+use rand::Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Heads,
+    Tails,
+}
+
+impl Side {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "heads" => Some(Self::Heads),
+            "tails" => Some(Self::Tails),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Side {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Heads => write!(f, "Heads"),
+            Self::Tails => write!(f, "Tails"),
+        }
+    }
+}
+
+pub fn flip(rng: &mut impl Rng) -> Side {
+    if rng.gen_bool(0.5) {
+        Side::Heads
+    } else {
+        Side::Tails
+    }
+}
+
+/// Returns the amount paid out to the player, which includes the bet.
+pub fn payout(bet: i64, choice: Side, result: Side) -> i64 {
+    if choice == result { bet * 2 } else { 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    #[test]
+    fn payout_on_win() {
+        assert_eq!(payout(10, Side::Heads, Side::Heads), 20);
+        assert_eq!(payout(10, Side::Heads, Side::Tails), 0);
+    }
+
+    #[test]
+    fn seeded_flip_is_deterministic() {
+        let flips = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..8).map(|_| flip(&mut rng)).collect::<Vec<_>>()
+        };
+
+        assert_eq!(flips(42), flips(42));
+    }
+}
@@ -0,0 +1,6 @@
+//! Game logic for the gambling commands. All randomness is passed in as an [`rand::Rng`], so the
+//! games can be tested deterministically with a seeded generator.
+
+pub mod blackjack;
+pub mod coinflip;
+pub mod slots;
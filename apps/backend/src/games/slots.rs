@@ -0,0 +1,83 @@
+use rand::Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Symbol {
+    pub emoji: &'static str,
+    /// Multiplier of the bet paid out for three of this symbol.
+    pub multiplier: i64,
+}
+
+pub const SYMBOLS: [Symbol; 6] = [
+    Symbol {
+        emoji: "🍒",
+        multiplier: 5,
+    },
+    Symbol {
+        emoji: "🍋",
+        multiplier: 5,
+    },
+    Symbol {
+        emoji: "🔔",
+        multiplier: 10,
+    },
+    Symbol {
+        emoji: "⭐",
+        multiplier: 15,
+    },
+    Symbol {
+        emoji: "💎",
+        multiplier: 25,
+    },
+    Symbol {
+        emoji: "7️⃣",
+        multiplier: 50,
+    },
+];
+
+pub fn spin(rng: &mut impl Rng) -> [Symbol; 3] {
+    [(); 3].map(|_| SYMBOLS[rng.gen_range(0..SYMBOLS.len())])
+}
+
+/// Returns the amount paid out to the player, which includes the bet. Three of a kind pays the
+/// multiplier of the symbol, any pair returns the bet.
+pub fn payout(bet: i64, reels: &[Symbol; 3]) -> i64 {
+    let [a, b, c] = reels;
+
+    if a == b && b == c {
+        bet * a.multiplier
+    } else if a == b || b == c || a == c {
+        bet
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    #[test]
+    fn three_of_a_kind() {
+        assert_eq!(payout(10, &[SYMBOLS[5]; 3]), 500);
+    }
+
+    #[test]
+    fn pair_returns_bet() {
+        assert_eq!(payout(10, &[SYMBOLS[0], SYMBOLS[1], SYMBOLS[0]]), 10);
+    }
+
+    #[test]
+    fn no_match() {
+        assert_eq!(payout(10, &[SYMBOLS[0], SYMBOLS[1], SYMBOLS[2]]), 0);
+    }
+
+    #[test]
+    fn seeded_spin_is_deterministic() {
+        let mut first = StdRng::seed_from_u64(7);
+        let mut second = StdRng::seed_from_u64(7);
+
+        assert_eq!(spin(&mut first), spin(&mut second));
+    }
+}
@@ -4,55 +4,70 @@ use crate::{
     AppState,
     env::ENV,
     error::Error,
-    models::api::ai::{
-        GenerateImageRequest, GenerateImageResponse, GenerateTextMessage, GenerateTextMessageRole,
-        GenerateTextRequest, GenerateTextResponse,
+    handlers::components::{AiConversationComponent, ComponentHandler},
+    models::{
+        api::ai::{GenerateImageRequest, GenerateImageResponse},
+        database::conversations::{Conversation, ConversationRepository},
     },
+    services::ai::{self, ConversationError, EditThrottle, TOKEN_BUDGET},
 };
 
-use super::CommandHandler;
+use super::{
+    CommandHandler,
+    options::{FromResolvedOptions, OptionError, Options},
+};
 
 use anyhow::anyhow;
-use regex::Regex;
-use reqwest::{StatusCode, Url};
+use reqwest::StatusCode;
 use serenity::all::{
-    CommandInteraction, CommandOptionType, CreateAttachment, CreateCommand, CreateCommandOption,
-    CreateInteractionResponseFollowup, InstallationContext, InteractionContext, ResolvedOption,
-    ResolvedValue, UserId,
+    Builder, CommandInteraction, CommandOptionType, CreateAttachment, CreateCommand,
+    CreateCommandOption, CreateInteractionResponseFollowup, EditInteractionResponse,
+    InstallationContext, InteractionContext, UserId,
 };
 
+enum AiOptions {
+    Text { prompt: String },
+    Reply { prompt: String },
+    Image { prompt: String },
+}
+
+impl FromResolvedOptions for AiOptions {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError> {
+        match options.subcommand()? {
+            ("text", options) => Ok(Self::Text {
+                prompt: options.get("prompt")?,
+            }),
+            ("reply", options) => Ok(Self::Reply {
+                prompt: options.get("prompt")?,
+            }),
+            ("image", options) => Ok(Self::Image {
+                prompt: options.get("prompt")?,
+            }),
+            (name, _) => Err(OptionError::UnknownSubcommand(name.to_string())),
+        }
+    }
+}
+
 pub struct AiCommand;
 
 impl CommandHandler for AiCommand {
+    const NAME: &'static str = "ai";
+
     async fn handle_command(
         interaction: CommandInteraction,
         state: Arc<AppState>,
     ) -> Result<(), crate::error::Error> {
         interaction.defer(&state.serenity_http).await?;
 
-        let options = interaction.data.options();
-
-        let ResolvedOption {
-            name,
-            value: ResolvedValue::SubCommand(options),
-            ..
-        } = options
-            .first()
-            .ok_or(anyhow!("Failed to get subcommand"))
-            .cloned()?
-        else {
-            return Err(anyhow!("Failed to get option value as subcommand"));
-        };
-
-        match name {
-            "text" => AiCommand::run_text(&interaction, &options, state).await,
-            "image" => AiCommand::run_image(&interaction, &options, state).await,
-            name => Err(anyhow!("Invalid subcommand name {}", name)),
+        match AiOptions::from_interaction(&interaction)? {
+            AiOptions::Text { prompt } => AiCommand::run_text(&interaction, &prompt, state).await,
+            AiOptions::Reply { prompt } => AiCommand::run_reply(&interaction, &prompt, state).await,
+            AiOptions::Image { prompt } => AiCommand::run_image(&interaction, &prompt, state).await,
         }
     }
 
     fn command() -> CreateCommand {
-        CreateCommand::new("ai")
+        CreateCommand::new(Self::NAME)
             .description("Liege AI")
             .integration_types(vec![InstallationContext::Guild, InstallationContext::User])
             .contexts(vec![
@@ -71,6 +86,21 @@ impl CommandHandler for AiCommand {
                         .required(true),
                     ),
             )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "reply",
+                    "Continue the latest conversation in this channel",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "prompt",
+                        "Prompt for the AI",
+                    )
+                    .required(true),
+                ),
+            )
             .add_option(
                 CreateCommandOption::new(
                     CommandOptionType::SubCommand,
@@ -90,109 +120,111 @@ impl CommandHandler for AiCommand {
 }
 
 impl AiCommand {
+    /// Starts a new conversation in the channel of the interaction.
     async fn run_text(
         interaction: &CommandInteraction,
-        subcommand_options: &Vec<ResolvedOption<'_>>,
+        prompt: &str,
+        state: Arc<AppState>,
+    ) -> Result<(), Error> {
+        let conversation = state
+            .database
+            .create_conversation(interaction.channel_id, interaction.user.id)
+            .await?;
+
+        Self::reply(&state, &interaction.token, &conversation, prompt).await
+    }
+
+    /// Continues the conversation in the channel of the interaction that was continued last.
+    async fn run_reply(
+        interaction: &CommandInteraction,
+        prompt: &str,
         state: Arc<AppState>,
     ) -> Result<(), Error> {
-        let &ResolvedOption {
-            value: ResolvedValue::String(prompt),
-            ..
-        } = subcommand_options
-            .first()
-            .ok_or(anyhow!("Failed to get prompt"))?
+        let Some(conversation) = state
+            .database
+            .latest_conversation(interaction.channel_id)
+            .await?
         else {
-            return Err(anyhow!("Failed to get prompt as string"));
+            interaction
+                .create_followup(
+                    &state.serenity_http,
+                    CreateInteractionResponseFollowup::new().content(
+                        "There is no conversation in this channel yet, start one with `/ai text`",
+                    ),
+                )
+                .await?;
+
+            return Ok(());
         };
 
-        let response = state
-            .http_client
-            .post("https://ai.nigga.church/v3/generate/text")
-            .header("Authorization", &ENV.ai_token)
-            .json(
-                &GenerateTextRequest::new()
-                    .model("perplexity-sonar-pro")
-                    .add_message(GenerateTextMessage::new(GenerateTextMessageRole::System, "You are Liege, a friendly and helpful chatbot designed to assist users with various inquiries. Your responses should be:
-
-1. **Concise & Relevant** - Provide clear, direct answers without unnecessary elaboration.  
-2. **Under 200 words** - Ensure every response stays within this limit. Trim excess details if needed.  
-3. **Engaging & Polite** - Maintain a friendly and professional tone.
-4. **Accurate & Informative** - Base your answers on verified information, avoiding speculation.  
-
-If a user request requires a longer response, summarize the key points."))
-                    .add_message(GenerateTextMessage::new(
-                        GenerateTextMessageRole::User,
-                        prompt,
-                    )),
-            )
-            .send()
-            .await?
-            .error_for_status()?;
+        Self::reply(&state, &interaction.token, &conversation, prompt).await
+    }
 
-        let raw_response = response
-            .json::<GenerateTextResponse>()
-            .await?
-            .choices
-            .get(0)
-            .and_then(|choice| choice.message.content.clone())
-            .unwrap_or("[empty response]".into());
-
-        let footnote_link_regex = Regex::new(r"\[[0-9]+\] \[.+\]\((.+)\)")?;
-        let link_matches: Vec<&str> = footnote_link_regex
-            .captures_iter(&raw_response)
-            .filter_map(|caps| caps.get(1).map(|m| m.as_str()))
-            .collect();
-
-        let footnote_list_regex = Regex::new(r"\n\n\n> \[0\][\S\s]*$")?;
-        let inline_footnote_regex = Regex::new(r"\[[0-9]+\]")?;
-
-        let replaced_response = footnote_list_regex.replace_all(&raw_response, "");
-        let replaced_response = inline_footnote_regex
-            .replace_all(&replaced_response, "")
-            .to_string();
-        let mut response = replaced_response.to_string();
-
-        let sources = link_matches
-            .into_iter()
-            .filter_map(|url_str| {
-                Url::parse(url_str)
-                    .ok()
-                    .and_then(|url| url.host_str().map(|host| format!("[{host}](<{url}>)")))
-            })
-            .collect::<Vec<_>>()
-            .join(", ");
-
-        if !sources.is_empty() {
-            response.push_str(&format!("\n-# > References: {sources}"));
-        }
+    /// Replies to `prompt` in `conversation` by editing the deferred response of the interaction
+    /// with `token` while the reply is generated. The final reply has a button to continue the
+    /// conversation as long as it has tokens left.
+    pub async fn reply(
+        state: &AppState,
+        token: &str,
+        conversation: &Conversation,
+        prompt: &str,
+    ) -> Result<(), Error> {
+        let mut throttle = EditThrottle::default();
 
-        response.truncate(2000);
+        let result = ai::reply(&state.database, &state.ai, conversation, prompt, |text| {
+            let edit = throttle.ready(&text);
 
-        interaction
-            .create_followup(
-                &state.serenity_http,
-                CreateInteractionResponseFollowup::new().content(response),
-            )
-            .await?;
+            async move {
+                if !edit {
+                    return Ok(());
+                }
+
+                let content = ai::format_response(&text)?;
+
+                if !content.trim().is_empty() {
+                    EditInteractionResponse::new()
+                        .content(content)
+                        .execute(&state.serenity_http, token)
+                        .await?;
+                }
+
+                Ok(())
+            }
+        })
+        .await;
+
+        let completion = match result {
+            Ok(completion) => completion,
+            Err(ConversationError::BudgetExceeded) => {
+                EditInteractionResponse::new()
+                    .content(ConversationError::BudgetExceeded.to_string())
+                    .execute(&state.serenity_http, token)
+                    .await?;
+
+                return Ok(());
+            }
+            Err(ConversationError::Other(error)) => return Err(error),
+        };
+
+        let mut response =
+            EditInteractionResponse::new().content(ai::format_response(&completion.content)?);
+
+        if conversation.tokens_used + completion.tokens < TOKEN_BUDGET {
+            response = response.components(vec![AiConversationComponent::action_row(Some(vec![
+                conversation.id.to_string(),
+            ]))]);
+        }
+
+        response.execute(&state.serenity_http, token).await?;
 
         Ok(())
     }
 
     async fn run_image(
         interaction: &CommandInteraction,
-        subcommand_options: &Vec<ResolvedOption<'_>>,
+        prompt: &str,
         state: Arc<AppState>,
     ) -> Result<(), crate::error::Error> {
-        let &ResolvedOption {
-            value: ResolvedValue::String(prompt),
-            ..
-        } = subcommand_options
-            .first()
-            .ok_or(anyhow!("Failed to get prompt"))?
-        else {
-            return Err(anyhow!("Failed to get prompt as string"));
-        };
-
         if interaction.user.id == UserId::new(778659522054717460) {
             interaction
                 .create_followup(
@@ -205,11 +237,11 @@ If a user request requires a longer response, summarize the key points."))
 
         let response = state
             .http_client
-            .post("https://ai.nigga.church/v3/generate/image")
+            .post(format!("{}/v3/generate/image", ENV.ai_api_url))
             .header("Authorization", &ENV.ai_token)
             .json(
                 &GenerateImageRequest::new()
-                    .source("flux-1-schnell")
+                    .source(&ENV.ai_image_model)
                     .prompt(prompt),
             )
             .send()
@@ -241,7 +273,7 @@ If a user request requires a longer response, summarize the key points."))
         }
 
         if let Some(data_url) = response.data_url {
-            let data_url = dataurl::DataUrl::parse(&data_url.as_str())
+            let data_url = dataurl::DataUrl::parse(data_url.as_str())
                 .map_err(|e| anyhow!("Failed to parse data URL: {e:?}"))?;
             let bytes = data_url.get_data();
 
@@ -2,44 +2,100 @@ use std::sync::Arc;
 
 use anyhow::anyhow;
 use serenity::all::{
-    Color, CommandInteraction, CommandOptionType, CreateCommand, CreateCommandOption, CreateEmbed,
-    CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseFollowup,
-    InstallationContext, InteractionContext, ResolvedOption, ResolvedValue,
+    Attachment, AutocompleteChoice, CommandInteraction, CommandOptionType,
+    CreateAutocompleteResponse, CreateCommand, CreateCommandOption, CreateInteractionResponse,
+    InstallationContext, InteractionContext,
 };
+use sqlx::types::Json;
 
 use crate::{
     AppState,
-    env::ENV,
     error::Error,
-    handlers::modals::{CodeModal, ModalHandler},
-    models::api::code::{ExecuteFile, ExecuteRequest, ExecuteResponse},
+    handlers::{
+        components::CodeSnippetComponent,
+        modals::{CodeModal, ModalHandler},
+    },
+    models::database::snippets::SnippetSource,
+    services::code,
 };
 
-use super::CommandHandler;
+use super::{
+    CommandHandler, followup_error,
+    options::{FromResolvedOptions, OptionError, Options},
+    respond_error,
+};
+
+/// Maximum size of an uploaded file in bytes.
+const MAX_FILE_SIZE: u32 = 512 * 1024;
+
+/// Maximum length of language names and versions, which are stored in the custom id of the
+/// code modal.
+const MAX_LANGUAGE_LENGTH: u16 = 32;
+
+/// Names of the options files can be uploaded with.
+const FILE_OPTIONS: [&str; 3] = ["file", "file2", "file3"];
+
+struct CodeOptions {
+    language: String,
+    version: Option<String>,
+    code: Option<String>,
+    files: Vec<Attachment>,
+}
+
+impl FromResolvedOptions for CodeOptions {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError> {
+        Ok(Self {
+            language: options.get("language")?,
+            version: options.get_optional("version")?,
+            code: options.get_optional("code")?,
+            files: FILE_OPTIONS
+                .iter()
+                .filter_map(|name| options.get_optional(name).transpose())
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
 
 pub struct CodeCommand;
 
 impl CommandHandler for CodeCommand {
+    const NAME: &'static str = "code";
+
     async fn handle_command(
         interaction: CommandInteraction,
         state: Arc<AppState>,
     ) -> Result<(), Error> {
-        let options = interaction.data.options();
-
-        let &ResolvedOption {
-            value: ResolvedValue::String(language),
-            ..
-        } = options.get(0).ok_or(anyhow!("Failed to get language"))?
-        else {
-            return Err(anyhow!("Failed to get language value as string"));
+        let CodeOptions {
+            language,
+            version,
+            code,
+            files,
+        } = CodeOptions::from_interaction(&interaction)?;
+
+        state.languages.ensure_loaded(&state.code_executor).await?;
+
+        let Some(runtime) = state.languages.resolve(&language) else {
+            let message = format!("Unknown language `{language}`");
+            return respond_error(&interaction, &state, &message).await;
+        };
+
+        let version = match version {
+            None => String::from("*"),
+            Some(version)
+                if version == "*" || state.languages.versions(&language).contains(&version) =>
+            {
+                version
+            }
+            Some(version) => {
+                let message = format!("Unknown version `{version}` of {}", runtime.language);
+                return respond_error(&interaction, &state, &message).await;
+            }
         };
 
-        let Some(&ResolvedOption {
-            value: ResolvedValue::String(code),
-            ..
-        }) = options.get(1)
-        else {
-            let modal = CodeModal::modal(Some(vec![language.to_string()]));
+        let language = runtime.language;
+
+        if code.is_none() && files.is_empty() {
+            let modal = CodeModal::modal(Some(vec![language, version]))?;
 
             interaction
                 .create_response(
@@ -49,84 +105,76 @@ impl CommandHandler for CodeCommand {
                 .await?;
 
             return Ok(());
-        };
+        }
 
+        if let Some(file) = files.iter().find(|file| file.size > MAX_FILE_SIZE) {
+            let message = format!(
+                "`{}` is too large, files can be at most {} KiB",
+                file.filename,
+                MAX_FILE_SIZE / 1024
+            );
+            return respond_error(&interaction, &state, &message).await;
+        }
+
+        // Downloading the files can take longer than the 3 seconds to respond
         interaction.defer(&state.serenity_http).await?;
 
-        let response = state
-            .http_client
-            .post("https://v2-api.nigga.church/code/execute")
-            .header("Authorization", &ENV.code_token)
-            .json(
-                &ExecuteRequest::new()
-                    .language(language)
-                    .version("*")
-                    .add_file(ExecuteFile::new().content(code)),
-            )
-            .send()
-            .await?
-            .error_for_status()?;
-
-        let response = response.json::<ExecuteResponse>().await?;
-
-        if response
-            .compile
-            .as_ref()
-            .is_some_and(|s| s.code.is_some_and(|c| c != 0))
-        {
-            let embed = CreateEmbed::new()
-                .color(Color::RED)
-                .title("Compile Error")
-                .description(format!("```\n{}\n```", {
-                    let compile = response.compile.as_ref().unwrap();
-
-                    if compile.output.is_empty() {
-                        format!("[Exited with code {}]", compile.code.unwrap())
-                    } else {
-                        compile.output.clone()
-                    }
-                }))
-                .footer(CreateEmbedFooter::new(format!(
-                    "Language: {} {}",
-                    response.language, response.version
-                )));
+        let mut downloads = vec![];
 
-            interaction
-                .create_followup(
-                    &state.serenity_http,
-                    CreateInteractionResponseFollowup::new().add_embed(embed),
-                )
+        for file in files {
+            let bytes = state
+                .http_client
+                .get(&file.url)
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
                 .await?;
-        } else {
-            let embed = CreateEmbed::new()
-                .color(Color::FOOYOO)
-                .title("Output")
-                .description(format!(
-                    "```\n{}\n```",
-                    if response.run.output.is_empty() {
-                        "No output, try logging the expression."
-                    } else {
-                        &response.run.output
-                    }
-                ))
-                .footer(CreateEmbedFooter::new(format!(
-                    "Language: {} {}",
-                    response.language, response.version
-                )));
 
-            interaction
-                .create_followup(
-                    &state.serenity_http,
-                    CreateInteractionResponseFollowup::new().add_embed(embed),
-                )
-                .await?;
+            downloads.push((file.filename, bytes.to_vec()));
         }
 
+        // Without code the first file is run, which has to be text to be shown and edited
+        let (name, code) = match code {
+            Some(code) => (None, code),
+            None => {
+                let (name, bytes) = downloads.remove(0);
+
+                match String::from_utf8(bytes) {
+                    Ok(code) => (Some(name), code),
+                    Err(_) => {
+                        return followup_error(&interaction, &state, "The first file must be text")
+                            .await;
+                    }
+                }
+            }
+        };
+
+        let source = SnippetSource {
+            language,
+            version,
+            code,
+            name,
+            files: Json(
+                downloads
+                    .into_iter()
+                    .map(|(name, bytes)| code::file_from_bytes(name, bytes))
+                    .collect(),
+            ),
+            ..Default::default()
+        };
+
+        let followup = CodeSnippetComponent::run(&state, interaction.user.id, source).await?;
+
+        interaction
+            .create_followup(&state.serenity_http, followup)
+            .await?;
+
         Ok(())
     }
 
     fn command() -> CreateCommand {
-        CreateCommand::new("code")
+        CreateCommand::new(Self::NAME)
             .description("Execute code")
             .integration_types(vec![InstallationContext::Guild, InstallationContext::User])
             .contexts(vec![
@@ -141,15 +189,97 @@ impl CommandHandler for CodeCommand {
                     "Programming language to use",
                 )
                 .required(true)
-                .add_string_choice("JavaScript", "js")
-                .add_string_choice("C++", "cpp")
-                .add_string_choice("Shell (bash)", "bash")
-                .add_string_choice("Rust", "rust")
-                .add_string_choice("Python", "python"),
+                .max_length(MAX_LANGUAGE_LENGTH)
+                .set_autocomplete(true),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "version",
+                    "Version of the language, defaults to the latest",
+                )
+                .required(false)
+                .max_length(MAX_LANGUAGE_LENGTH)
+                .set_autocomplete(true),
             )
             .add_option(
                 CreateCommandOption::new(CommandOptionType::String, "code", "The code to execute")
                     .required(false),
             )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::Attachment,
+                FILE_OPTIONS[0],
+                "File to send along, run instead if there is no code",
+            ))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::Attachment,
+                FILE_OPTIONS[1],
+                "Another file to send along",
+            ))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::Attachment,
+                FILE_OPTIONS[2],
+                "Another file to send along",
+            ))
+    }
+
+    async fn autocomplete(
+        interaction: CommandInteraction,
+        state: Arc<AppState>,
+    ) -> Result<(), Error> {
+        let focused = interaction
+            .data
+            .autocomplete()
+            .ok_or(anyhow!("Failed to get focused option"))?;
+
+        state.languages.ensure_loaded(&state.code_executor).await?;
+
+        // Discord rejects autocomplete responses with more than 25 choices
+        let choices = match focused.name {
+            "language" => state
+                .languages
+                .search(focused.value)
+                .into_iter()
+                .take(25)
+                .map(|language| {
+                    AutocompleteChoice::new(
+                        format!("{} {}", language.language, language.version),
+                        language.language,
+                    )
+                })
+                .collect(),
+            "version" => {
+                let language = interaction
+                    .data
+                    .options
+                    .iter()
+                    .find(|option| option.name == "language")
+                    .and_then(|option| option.value.as_str())
+                    .unwrap_or_default();
+
+                state
+                    .languages
+                    .versions(language)
+                    .into_iter()
+                    .filter(|version| version.starts_with(focused.value))
+                    .take(25)
+                    .map(|version| AutocompleteChoice::new(version.clone(), version))
+                    .collect()
+            }
+            name => {
+                return Err(anyhow!("Option '{name}' does not support autocomplete"));
+            }
+        };
+
+        interaction
+            .create_response(
+                &state.serenity_http,
+                CreateInteractionResponse::Autocomplete(
+                    CreateAutocompleteResponse::new().set_choices(choices),
+                ),
+            )
+            .await?;
+
+        Ok(())
     }
 }
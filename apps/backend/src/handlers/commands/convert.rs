@@ -0,0 +1,178 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use serenity::all::{
+    AutocompleteChoice, Color, CommandInteraction, CommandOptionType, CreateAutocompleteResponse,
+    CreateCommand, CreateCommandOption, CreateEmbed, CreateEmbedFooter, CreateInteractionResponse,
+    CreateInteractionResponseFollowup, InstallationContext, InteractionContext,
+};
+
+use crate::{AppState, error::Error};
+
+use super::{
+    CommandHandler, followup_error,
+    options::{FromResolvedOptions, OptionError, Options},
+};
+
+/// Maximum length of unit names, far longer than any unit of numbat.
+const MAX_UNIT_LENGTH: u16 = 64;
+
+struct ConvertOptions {
+    value: f64,
+    from: String,
+    to: String,
+}
+
+impl FromResolvedOptions for ConvertOptions {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError> {
+        Ok(Self {
+            value: options.get("value")?,
+            from: options.get("from")?,
+            to: options.get("to")?,
+        })
+    }
+}
+
+pub struct ConvertCommand;
+
+impl CommandHandler for ConvertCommand {
+    const NAME: &'static str = "convert";
+
+    async fn handle_command(
+        interaction: CommandInteraction,
+        state: Arc<AppState>,
+    ) -> Result<(), Error> {
+        let options = ConvertOptions::from_interaction(&interaction)?;
+
+        interaction.defer(&state.serenity_http).await?;
+
+        let conversion = match state
+            .math_sessions
+            .convert(options.value, &options.from, &options.to)
+            .await
+        {
+            Ok(conversion) => conversion,
+            Err(error) => return followup_error(&interaction, &state, &error.to_string()).await,
+        };
+
+        let mut embed = CreateEmbed::new()
+            .color(Color::FOOYOO)
+            .description(format!(
+                "{} {} = **{}**",
+                options.value, options.from, conversion.result
+            ))
+            .footer(CreateEmbedFooter::new(conversion.dimension));
+
+        if !conversion.related.is_empty() {
+            embed = embed.field("In other units", conversion.related.join("\n"), false);
+        }
+
+        interaction
+            .create_followup(
+                &state.serenity_http,
+                CreateInteractionResponseFollowup::new().embed(embed),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    fn command() -> CreateCommand {
+        CreateCommand::new(Self::NAME)
+            .description("Convert a value between units")
+            .integration_types(vec![InstallationContext::Guild, InstallationContext::User])
+            .contexts(vec![
+                InteractionContext::Guild,
+                InteractionContext::BotDm,
+                InteractionContext::PrivateChannel,
+            ])
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Number,
+                    "value",
+                    "The value to convert",
+                )
+                .required(true),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "from",
+                    "The unit of the value, e.g. mile",
+                )
+                .required(true)
+                .max_length(MAX_UNIT_LENGTH)
+                .set_autocomplete(true),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "to",
+                    "The unit to convert into, e.g. km",
+                )
+                .required(true)
+                .max_length(MAX_UNIT_LENGTH)
+                .set_autocomplete(true),
+            )
+    }
+
+    async fn autocomplete(
+        interaction: CommandInteraction,
+        state: Arc<AppState>,
+    ) -> Result<(), Error> {
+        let focused = interaction
+            .data
+            .autocomplete()
+            .ok_or(anyhow!("Failed to get focused option"))?;
+
+        let units = state.units.search(focused.value);
+
+        // Discord rejects autocomplete responses with more than 25 choices
+        let choices = match focused.name {
+            "from" => units
+                .take(25)
+                .map(|unit| {
+                    AutocompleteChoice::new(
+                        format!("{} ({}) • {}", unit.name, unit.symbol, unit.dimension),
+                        unit.symbol.clone(),
+                    )
+                })
+                .collect(),
+            "to" => {
+                // Only units of the same dimension can be converted into each other
+                let from = interaction
+                    .data
+                    .options
+                    .iter()
+                    .find(|option| option.name == "from")
+                    .and_then(|option| option.value.as_str())
+                    .and_then(|name| state.units.resolve(name));
+
+                units
+                    .filter(|unit| from.is_none_or(|from| from.is_compatible(unit)))
+                    .take(25)
+                    .map(|unit| {
+                        AutocompleteChoice::new(
+                            format!("{} ({}) • {}", unit.name, unit.symbol, unit.dimension),
+                            unit.symbol.clone(),
+                        )
+                    })
+                    .collect()
+            }
+            name => {
+                return Err(anyhow!("Option '{name}' does not support autocomplete"));
+            }
+        };
+
+        interaction
+            .create_response(
+                &state.serenity_http,
+                CreateInteractionResponse::Autocomplete(
+                    CreateAutocompleteResponse::new().set_choices(choices),
+                ),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
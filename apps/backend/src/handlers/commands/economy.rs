@@ -0,0 +1,214 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use serenity::all::{
+    Color, CommandInteraction, CommandOptionType, CreateCommand, CreateCommandOption, CreateEmbed,
+    CreateInteractionResponse, CreateInteractionResponseMessage, InstallationContext,
+    InteractionContext, User,
+};
+
+use crate::{
+    AppState,
+    error::Error,
+    models::database::economy::{DailyClaim, EconomyError, EconomyRepository},
+};
+
+use super::{
+    CommandHandler,
+    options::{FromResolvedOptions, OptionError, Options},
+    respond_error,
+};
+
+const DAILY_REWARD: i64 = 100;
+const DAILY_COOLDOWN: Duration = Duration::hours(24);
+const LEADERBOARD_SIZE: i64 = 10;
+
+enum EconomyOptions {
+    Balance { user: Option<User> },
+    Daily,
+    Transfer { user: User, amount: i64 },
+    Leaderboard,
+}
+
+impl FromResolvedOptions for EconomyOptions {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError> {
+        match options.subcommand()? {
+            ("balance", options) => Ok(Self::Balance {
+                user: options.get_optional("user")?,
+            }),
+            ("daily", _) => Ok(Self::Daily),
+            ("transfer", options) => Ok(Self::Transfer {
+                user: options.get("user")?,
+                amount: options.get("amount")?,
+            }),
+            ("leaderboard", _) => Ok(Self::Leaderboard),
+            (name, _) => Err(OptionError::UnknownSubcommand(name.to_string())),
+        }
+    }
+}
+
+pub struct EconomyCommand;
+
+impl CommandHandler for EconomyCommand {
+    const NAME: &'static str = "economy";
+
+    async fn handle_command(
+        interaction: CommandInteraction,
+        state: Arc<AppState>,
+    ) -> Result<(), Error> {
+        let embed = match EconomyOptions::from_interaction(&interaction)? {
+            EconomyOptions::Balance { user } => {
+                let user = user.unwrap_or(interaction.user.clone());
+                let wallet = state.database.get_wallet(user.id).await?;
+
+                CreateEmbed::new()
+                    .color(Color::FOOYOO)
+                    .description(format!("<@{}> has **{}** coins", user.id, wallet.balance))
+            }
+
+            EconomyOptions::Daily => {
+                let claim = state
+                    .database
+                    .claim_daily(
+                        interaction.user.id,
+                        DAILY_REWARD,
+                        DAILY_COOLDOWN,
+                        Utc::now(),
+                    )
+                    .await?;
+
+                match claim {
+                    DailyClaim::Claimed { balance } => CreateEmbed::new()
+                        .color(Color::FOOYOO)
+                        .description(format!(
+                            "You claimed your daily **{DAILY_REWARD}** coins, you now have **{balance}** coins"
+                        )),
+                    DailyClaim::OnCooldown { available_at } => CreateEmbed::new()
+                        .color(Color::RED)
+                        .description(format!(
+                            "You already claimed your daily reward, come back <t:{}:R>",
+                            available_at.timestamp()
+                        )),
+                }
+            }
+
+            EconomyOptions::Transfer { user, amount } => {
+                if user.bot {
+                    return respond_error(&interaction, &state, "You can not pay bots").await;
+                }
+
+                let result = state
+                    .database
+                    .transfer(interaction.user.id, user.id, amount)
+                    .await;
+
+                match result {
+                    Ok((balance, _)) => CreateEmbed::new().color(Color::FOOYOO).description(
+                        format!(
+                            "You sent **{amount}** coins to <@{}>, you now have **{balance}** coins",
+                            user.id
+                        ),
+                    ),
+                    Err(error) => match error.downcast_ref::<EconomyError>() {
+                        Some(error) => {
+                            let message = format!("Transfer failed: {error}");
+                            return respond_error(&interaction, &state, &message).await;
+                        }
+                        None => return Err(error),
+                    },
+                }
+            }
+
+            EconomyOptions::Leaderboard => {
+                let wallets = state.database.leaderboard(LEADERBOARD_SIZE).await?;
+
+                let description = if wallets.is_empty() {
+                    String::from("Nobody has any coins yet")
+                } else {
+                    wallets
+                        .iter()
+                        .enumerate()
+                        .map(|(i, wallet)| {
+                            format!(
+                                "{}. <@{}> - **{}** coins",
+                                i + 1,
+                                wallet.user_id(),
+                                wallet.balance
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                };
+
+                CreateEmbed::new()
+                    .color(Color::FOOYOO)
+                    .title("Leaderboard")
+                    .description(description)
+            }
+        };
+
+        interaction
+            .create_response(
+                &state.serenity_http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new().embed(embed),
+                ),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    fn command() -> CreateCommand {
+        CreateCommand::new(Self::NAME)
+            .description("Coins, daily rewards and transfers")
+            .integration_types(vec![InstallationContext::Guild, InstallationContext::User])
+            .contexts(vec![
+                InteractionContext::Guild,
+                InteractionContext::BotDm,
+                InteractionContext::PrivateChannel,
+            ])
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "balance",
+                    "Show the balance of a user",
+                )
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::User,
+                    "user",
+                    "User to show the balance of, defaults to you",
+                )),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "daily",
+                "Claim your daily reward",
+            ))
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "transfer",
+                    "Send coins to another user",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::User, "user", "Receiving user")
+                        .required(true),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::Integer,
+                        "amount",
+                        "Amount of coins to send",
+                    )
+                    .min_int_value(1)
+                    .required(true),
+                ),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "leaderboard",
+                "Show the richest users",
+            ))
+    }
+}
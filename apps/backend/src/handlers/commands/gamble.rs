@@ -0,0 +1,226 @@
+use std::sync::Arc;
+
+use rand::{SeedableRng, rngs::StdRng};
+use serenity::all::{
+    Color, CommandInteraction, CommandOptionType, CreateCommand, CreateCommandOption, CreateEmbed,
+    CreateInteractionResponse, CreateInteractionResponseMessage, InstallationContext,
+    InteractionContext,
+};
+
+use crate::{
+    AppState,
+    error::Error,
+    games::{blackjack::Blackjack, coinflip, slots},
+    handlers::components::BlackjackComponent,
+    models::database::{economy::EconomyError, gambling::GamblingRepository},
+};
+
+use super::{
+    CommandHandler,
+    options::{FromResolvedOptions, OptionError, Options},
+    respond_error,
+};
+
+enum GambleOptions {
+    Coinflip { side: coinflip::Side, bet: i64 },
+    Slots { bet: i64 },
+    Blackjack { bet: i64 },
+}
+
+impl FromResolvedOptions for GambleOptions {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError> {
+        match options.subcommand()? {
+            ("coinflip", options) => {
+                let side: String = options.get("side")?;
+
+                Ok(Self::Coinflip {
+                    side: coinflip::Side::from_name(&side).ok_or(OptionError::InvalidType {
+                        name: String::from("side"),
+                        expected: "heads or tails",
+                    })?,
+                    bet: options.get("bet")?,
+                })
+            }
+            ("slots", options) => Ok(Self::Slots {
+                bet: options.get("bet")?,
+            }),
+            ("blackjack", options) => Ok(Self::Blackjack {
+                bet: options.get("bet")?,
+            }),
+            (name, _) => Err(OptionError::UnknownSubcommand(name.to_string())),
+        }
+    }
+}
+
+pub struct GambleCommand;
+
+impl CommandHandler for GambleCommand {
+    const NAME: &'static str = "gamble";
+
+    async fn handle_command(
+        interaction: CommandInteraction,
+        state: Arc<AppState>,
+    ) -> Result<(), Error> {
+        let options = GambleOptions::from_interaction(&interaction)?;
+        let mut rng = StdRng::from_entropy();
+
+        let result = match options {
+            GambleOptions::Coinflip { side, bet } => {
+                let result = coinflip::flip(&mut rng);
+                let payout = coinflip::payout(bet, side, result);
+
+                Self::play_round(&interaction, &state, bet, payout, |balance| {
+                    format!(
+                        "The coin landed on **{result}**. {}\nYou now have **{balance}** coins",
+                        Self::payout_text(bet, payout)
+                    )
+                })
+                .await
+            }
+
+            GambleOptions::Slots { bet } => {
+                let reels = slots::spin(&mut rng);
+                let payout = slots::payout(bet, &reels);
+
+                Self::play_round(&interaction, &state, bet, payout, |balance| {
+                    format!(
+                        "{}\n{}\nYou now have **{balance}** coins",
+                        reels.map(|symbol| symbol.emoji).join(" | "),
+                        Self::payout_text(bet, payout)
+                    )
+                })
+                .await
+            }
+
+            GambleOptions::Blackjack { bet } => {
+                let game = Blackjack::new(bet, &mut rng);
+                Self::start_blackjack(&interaction, &state, game).await
+            }
+        };
+
+        match result {
+            Err(error) if error.downcast_ref::<EconomyError>().is_some() => {
+                let message = format!("You can not place this bet: {error}");
+                respond_error(&interaction, &state, &message).await
+            }
+            result => result,
+        }
+    }
+
+    fn command() -> CreateCommand {
+        let bet_option = || {
+            CreateCommandOption::new(CommandOptionType::Integer, "bet", "Amount of coins to bet")
+                .min_int_value(1)
+                .required(true)
+        };
+
+        CreateCommand::new(Self::NAME)
+            .description("Bet your coins")
+            .integration_types(vec![InstallationContext::Guild, InstallationContext::User])
+            .contexts(vec![
+                InteractionContext::Guild,
+                InteractionContext::BotDm,
+                InteractionContext::PrivateChannel,
+            ])
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "coinflip",
+                    "Double your bet if you guess the right side",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "side", "Side to bet on")
+                        .add_string_choice("Heads", "heads")
+                        .add_string_choice("Tails", "tails")
+                        .required(true),
+                )
+                .add_sub_option(bet_option()),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "slots",
+                    "Spin the slot machine",
+                )
+                .add_sub_option(bet_option()),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "blackjack",
+                    "Play a round of blackjack against the dealer",
+                )
+                .add_sub_option(bet_option()),
+            )
+    }
+}
+
+impl GambleCommand {
+    async fn play_round(
+        interaction: &CommandInteraction,
+        state: &Arc<AppState>,
+        bet: i64,
+        payout: i64,
+        description: impl FnOnce(i64) -> String,
+    ) -> Result<(), Error> {
+        let balance = state
+            .database
+            .play_round(interaction.user.id, bet, payout)
+            .await?;
+
+        let color = if payout >= bet {
+            Color::FOOYOO
+        } else {
+            Color::RED
+        };
+
+        interaction
+            .create_response(
+                &state.serenity_http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new().embed(
+                        CreateEmbed::new()
+                            .color(color)
+                            .description(description(balance)),
+                    ),
+                ),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn start_blackjack(
+        interaction: &CommandInteraction,
+        state: &Arc<AppState>,
+        game: Blackjack,
+    ) -> Result<(), Error> {
+        let game_id = state
+            .database
+            .create_blackjack_game(interaction.user.id, &game)
+            .await?;
+
+        interaction
+            .create_response(
+                &state.serenity_http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .embed(BlackjackComponent::embed(&game))
+                        .components(BlackjackComponent::components(game_id, &game)),
+                ),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    fn payout_text(bet: i64, payout: i64) -> String {
+        if payout > bet {
+            format!("You won **{}** coins!", payout - bet)
+        } else if payout == bet {
+            String::from("You got your bet back")
+        } else {
+            format!("You lost **{bet}** coins")
+        }
+    }
+}
@@ -1,78 +1,543 @@
 use std::sync::Arc;
 
-use anyhow::anyhow;
 use serenity::all::{
-    Color, CommandInteraction, CommandOptionType, CreateCommand, CreateCommandOption, CreateEmbed,
-    CreateInteractionResponse, CreateInteractionResponseMessage, InstallationContext,
-    InteractionContext, ResolvedOption, ResolvedValue,
+    Color, CommandInteraction, CommandOptionType, CreateAttachment, CreateCommand,
+    CreateCommandOption, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseFollowup,
+    CreateInteractionResponseMessage, InstallationContext, InteractionContext,
 };
 
-use crate::{AppState, error::Error, math};
+use crate::{
+    AppState,
+    error::Error,
+    handlers::modals::{MAX_MODULES, MathModuleModal, ModuleError},
+    math::{
+        self, Modules,
+        render::{render_markup, render_plot},
+    },
+    models::database::math_modules::{MathModule, MathModuleRepository, ModuleOwner},
+};
+
+use super::{
+    CommandHandler, followup_error,
+    options::{FromResolvedOptions, OptionError, Options},
+    respond_error,
+};
+
+/// Maximum length of the definition list, embed descriptions are limited to 4096 characters.
+const MAX_DEFINITIONS_LENGTH: usize = 3800;
 
-use super::CommandHandler;
+/// Maximum length of a list of modules, embed field values are limited to 1024 characters.
+const MAX_MODULE_LIST_LENGTH: usize = 1000;
+
+/// Maximum length of the alt text of images, limited to 1024 characters by Discord.
+const MAX_ALT_TEXT_LENGTH: usize = 1000;
+
+const DOCUMENTATION: &str = "-# For more information on how to use this command, [view the documentation](<https://numbat.dev/doc/>)";
+
+enum MathOptions {
+    Evaluate {
+        expression: String,
+    },
+    Plot {
+        function: String,
+        from: f64,
+        to: f64,
+    },
+    Reset,
+    Vars,
+    ModuleSave {
+        name: String,
+        shared: bool,
+    },
+    ModuleList,
+    ModuleDelete {
+        name: String,
+        shared: bool,
+    },
+}
+
+impl FromResolvedOptions for MathOptions {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError> {
+        match options.subcommand()? {
+            ("evaluate", options) => Ok(Self::Evaluate {
+                expression: options.get("expression")?,
+            }),
+            ("plot", options) => Ok(Self::Plot {
+                function: options.get("function")?,
+                from: options.get("from")?,
+                to: options.get("to")?,
+            }),
+            ("reset", _) => Ok(Self::Reset),
+            ("vars", _) => Ok(Self::Vars),
+            ("module", options) => match options.subcommand()? {
+                ("save", options) => Ok(Self::ModuleSave {
+                    name: options.get("name")?,
+                    shared: options.get_optional("shared")?.unwrap_or(false),
+                }),
+                ("list", _) => Ok(Self::ModuleList),
+                ("delete", options) => Ok(Self::ModuleDelete {
+                    name: options.get("name")?,
+                    shared: options.get_optional("shared")?.unwrap_or(false),
+                }),
+                (name, _) => Err(OptionError::UnknownSubcommand(name.to_string())),
+            },
+            (name, _) => Err(OptionError::UnknownSubcommand(name.to_string())),
+        }
+    }
+}
 
 pub struct MathCommand;
 
 impl CommandHandler for MathCommand {
+    const NAME: &'static str = "math";
+
     async fn handle_command(
         interaction: CommandInteraction,
         state: Arc<AppState>,
     ) -> Result<(), Error> {
-        let options = interaction.data.options();
-
-        let &ResolvedOption {
-            value: ResolvedValue::String(expression),
-            ..
-        } = options.first().ok_or(anyhow!("Failed to get expression"))?
-        else {
-            return Err(anyhow!("Failed to get expression value as string"));
-        };
+        let user_id = interaction.user.id;
+
+        let embed = match MathOptions::from_interaction(&interaction)? {
+            MathOptions::Evaluate { expression } => {
+                interaction.defer(&state.serenity_http).await?;
+
+                let followup = Self::evaluate(&state, &interaction, &expression).await?;
 
-        let result = math::evaluate(expression);
-        let is_ok = result.is_ok();
-
-        let content = format!(
-            "**Expression:**\n```\n{}\n```\n**{}**:\n```{}\n```\n-# For more information on how to use this command, [view the documentation](<https://numbat.dev/doc/>)",
-            expression,
-            if is_ok { "Result" } else { "Error" },
-            if is_ok {
-                result.unwrap().to_string()
-            } else {
-                result.unwrap_err().to_string()
+                interaction
+                    .create_followup(&state.serenity_http, followup)
+                    .await?;
+
+                return Ok(());
             }
-        );
 
-        let color = if is_ok { Color::FOOYOO } else { Color::RED };
+            MathOptions::Plot { function, from, to } => {
+                interaction.defer(&state.serenity_http).await?;
+
+                let followup = Self::plot(&state, &interaction, &function, from, to).await?;
+
+                interaction
+                    .create_followup(&state.serenity_http, followup)
+                    .await?;
+
+                return Ok(());
+            }
+
+            MathOptions::Reset => {
+                let description = if state.math_sessions.reset(user_id) {
+                    "Your variables and functions were cleared"
+                } else {
+                    "You have not defined anything yet"
+                };
+
+                CreateEmbed::new()
+                    .color(Color::FOOYOO)
+                    .description(description)
+            }
+
+            MathOptions::Vars => {
+                interaction.defer(&state.serenity_http).await?;
+
+                let definitions = match state.math_sessions.definitions(user_id).await {
+                    Ok(definitions) => definitions,
+                    Err(error) => {
+                        return followup_error(&interaction, &state, &error.to_string()).await;
+                    }
+                };
+
+                let description = if definitions.is_empty() {
+                    String::from(
+                        "You have not defined anything yet, try `/math evaluate let x = 5 m`",
+                    )
+                } else {
+                    let mut list = String::new();
+
+                    for definition in &definitions {
+                        let line = format!("{} = {}\n", definition.name, definition.value);
+
+                        if list.len() + line.len() > MAX_DEFINITIONS_LENGTH {
+                            list.push_str("...\n");
+                            break;
+                        }
+
+                        list.push_str(&line);
+                    }
+
+                    format!("```\n{list}```")
+                };
+
+                let embed = CreateEmbed::new()
+                    .color(Color::FOOYOO)
+                    .title("Your variables and functions")
+                    .description(description);
+
+                interaction
+                    .create_followup(
+                        &state.serenity_http,
+                        CreateInteractionResponseFollowup::new().embed(embed),
+                    )
+                    .await?;
+
+                return Ok(());
+            }
+
+            MathOptions::ModuleSave { name, shared } => {
+                let permissions = interaction
+                    .member
+                    .as_ref()
+                    .and_then(|member| member.permissions);
+
+                let result = async {
+                    let owner = MathModuleModal::owner(user_id, interaction.guild_id, shared)?;
+                    let existing =
+                        MathModuleModal::authorize(&state, owner, &name, user_id, permissions)
+                            .await?;
+
+                    Ok::<_, ModuleError>((owner, existing))
+                }
+                .await;
+
+                let (owner, existing) = match result {
+                    Ok(result) => result,
+                    Err(ModuleError::Other(error)) => return Err(error),
+                    Err(error) => {
+                        return respond_error(&interaction, &state, &error.to_string()).await;
+                    }
+                };
+
+                // The code is entered in a modal since command options can not span lines
+                let code = existing.map(|module| module.code).unwrap_or_default();
+                let modal = MathModuleModal::prefilled(owner, &name, &code)?;
+
+                interaction
+                    .create_response(
+                        &state.serenity_http,
+                        CreateInteractionResponse::Modal(modal),
+                    )
+                    .await?;
+
+                return Ok(());
+            }
+
+            MathOptions::ModuleList => {
+                let mut embed = CreateEmbed::new()
+                    .color(Color::FOOYOO)
+                    .title("Modules")
+                    .description(format!(
+                        "Import modules with `use name`, at most {MAX_MODULES} can be saved"
+                    ));
+
+                let mut owners = vec![("Your modules", ModuleOwner::User(user_id))];
+
+                if let Some(guild_id) = interaction.guild_id {
+                    owners.push(("Server modules", ModuleOwner::Guild(guild_id)));
+                }
+
+                for (title, owner) in owners {
+                    let modules = state.database.list_modules(owner).await?;
+                    embed = embed.field(title, module_list(&modules), false);
+                }
+
+                embed
+            }
+
+            MathOptions::ModuleDelete { name, shared } => {
+                let owner = match MathModuleModal::owner(user_id, interaction.guild_id, shared) {
+                    Ok(owner) => owner,
+                    Err(error) => {
+                        return respond_error(&interaction, &state, &error.to_string()).await;
+                    }
+                };
+
+                let Some(module) = state.database.get_module(owner, &name).await? else {
+                    let message = format!("There is no module named `{name}`");
+                    return respond_error(&interaction, &state, &message).await;
+                };
+
+                let permissions = interaction
+                    .member
+                    .as_ref()
+                    .and_then(|member| member.permissions);
+
+                if !MathModuleModal::can_change(&module, user_id, permissions) {
+                    let message = ModuleError::NotAllowed(name).to_string();
+                    return respond_error(&interaction, &state, &message).await;
+                }
+
+                state.database.delete_module(owner, &name).await?;
+
+                CreateEmbed::new()
+                    .color(Color::FOOYOO)
+                    .description(format!("Deleted `{name}`"))
+            }
+        };
 
         interaction
             .create_response(
                 &state.serenity_http,
                 CreateInteractionResponse::Message(
-                    CreateInteractionResponseMessage::new()
-                        .embed(CreateEmbed::new().color(color).description(content)),
+                    CreateInteractionResponseMessage::new().embed(embed),
                 ),
             )
-            .await
-            .map_err(Error::from)?;
-
-        println!("Passed Math");
+            .await?;
 
         Ok(())
     }
 
     fn command() -> CreateCommand {
-        CreateCommand::new("math")
-            .description("Evaluate a math expression")
+        CreateCommand::new(Self::NAME)
+            .description("Evaluate math expressions, definitions are kept between calls")
             .integration_types(vec![InstallationContext::Guild, InstallationContext::User])
             .contexts(vec![
                 InteractionContext::Guild,
                 InteractionContext::BotDm,
                 InteractionContext::PrivateChannel,
             ])
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "evaluate",
+                    "Evaluate a math expression",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "expression",
+                        "The expression to evaluate",
+                    )
+                    .required(true),
+                ),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "plot",
+                    "Plot a function of x",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "function",
+                        "The function to plot, e.g. sin(x) or x^2",
+                    )
+                    .required(true),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::Number,
+                        "from",
+                        "The start of the range of x",
+                    )
+                    .required(true),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::Number,
+                        "to",
+                        "The end of the range of x",
+                    )
+                    .required(true),
+                ),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "reset",
+                "Clear your variables and functions",
+            ))
             .add_option(CreateCommandOption::new(
-                CommandOptionType::String,
-                "expression",
-                "The expression to evaluate",
+                CommandOptionType::SubCommand,
+                "vars",
+                "Show your variables and functions",
             ))
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommandGroup,
+                    "module",
+                    "Manage modules that can be imported with `use`",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "save",
+                        "Create or edit a module",
+                    )
+                    .add_sub_option(module_name_option())
+                    .add_sub_option(module_shared_option()),
+                )
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "list",
+                    "Show the modules you can import",
+                ))
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::SubCommand,
+                        "delete",
+                        "Delete a module",
+                    )
+                    .add_sub_option(module_name_option())
+                    .add_sub_option(module_shared_option()),
+                ),
+            )
+    }
+}
+
+impl MathCommand {
+    /// Returns the modules the invoking user can import, their own and the ones of the guild.
+    async fn modules(state: &AppState, interaction: &CommandInteraction) -> Result<Modules, Error> {
+        let modules = state
+            .database
+            .importable_modules(interaction.user.id, interaction.guild_id)
+            .await?;
+
+        Ok(modules.into_iter().collect())
+    }
+
+    /// Evaluates `expression`, showing the result as a highlighted image.
+    async fn evaluate(
+        state: &AppState,
+        interaction: &CommandInteraction,
+        expression: &str,
+    ) -> Result<CreateInteractionResponseFollowup, Error> {
+        let modules = Self::modules(state, interaction).await?;
+
+        let markup = match state
+            .math_sessions
+            .with_modules(modules)
+            .evaluate_markup(interaction.user.id, expression)
+            .await
+        {
+            Ok(markup) => markup,
+            Err(error) => {
+                let embed = CreateEmbed::new().color(Color::RED).description(format!(
+                    "**Expression:**\n```\n{expression}\n```\n**Error**:\n```{error}\n```\n{DOCUMENTATION}"
+                ));
+
+                return Ok(CreateInteractionResponseFollowup::new().embed(embed));
+            }
+        };
+
+        let result = markup.to_string().trim().to_string();
+
+        // Definitions have no result, which is shown as an empty block instead of an empty image
+        if result.is_empty() {
+            let embed = CreateEmbed::new().color(Color::FOOYOO).description(format!(
+                "**Expression:**\n```\n{expression}\n```\n**Result**:\n```\n```\n{DOCUMENTATION}"
+            ));
+
+            return Ok(CreateInteractionResponseFollowup::new().embed(embed));
+        }
+
+        let alt_text: String = result.chars().take(MAX_ALT_TEXT_LENGTH).collect();
+
+        // Rendering takes too long for the runtime with a lot of output
+        let image = tokio::task::spawn_blocking(move || render_markup(&markup)).await??;
+        let image = CreateAttachment::bytes(image, "result.png").description(alt_text);
+
+        let embed = CreateEmbed::new()
+            .color(Color::FOOYOO)
+            .description(format!(
+                "**Expression:**\n```\n{expression}\n```\n**Result**:\n{DOCUMENTATION}"
+            ))
+            .attachment("result.png");
+
+        Ok(CreateInteractionResponseFollowup::new()
+            .embed(embed)
+            .add_file(image))
     }
+
+    /// Plots `function` from `from` to `to` as a line chart.
+    async fn plot(
+        state: &AppState,
+        interaction: &CommandInteraction,
+        function: &str,
+        from: f64,
+        to: f64,
+    ) -> Result<CreateInteractionResponseFollowup, Error> {
+        let description = format!("**Function:**\n```\n{function}\n```\n**Range:** {from} to {to}");
+        let modules = Self::modules(state, interaction).await?;
+
+        let plot = match state
+            .math_sessions
+            .with_modules(modules)
+            .plot(interaction.user.id, function, from, to)
+            .await
+        {
+            Ok(plot) => plot,
+            Err(error) => {
+                let embed = CreateEmbed::new().color(Color::RED).description(format!(
+                    "{description}\n**Error**:\n```{error}\n```\n{DOCUMENTATION}"
+                ));
+
+                return Ok(CreateInteractionResponseFollowup::new().embed(embed));
+            }
+        };
+
+        let image = match tokio::task::spawn_blocking(move || render_plot(&plot)).await? {
+            Ok(image) => image,
+            Err(error) => {
+                let embed = CreateEmbed::new().color(Color::RED).description(format!(
+                    "{description}\n**Error**:\n```{error}\n```\n{DOCUMENTATION}"
+                ));
+
+                return Ok(CreateInteractionResponseFollowup::new().embed(embed));
+            }
+        };
+
+        let image = CreateAttachment::bytes(image, "plot.png")
+            .description(format!("Plot of {function} from {from} to {to}"));
+
+        let embed = CreateEmbed::new()
+            .color(Color::FOOYOO)
+            .description(format!("{description}\n{DOCUMENTATION}"))
+            .attachment("plot.png");
+
+        Ok(CreateInteractionResponseFollowup::new()
+            .embed(embed)
+            .add_file(image))
+    }
+}
+
+fn module_name_option() -> CreateCommandOption {
+    CreateCommandOption::new(
+        CommandOptionType::String,
+        "name",
+        "The name the module is imported by, e.g. team::constants",
+    )
+    .max_length(math::MAX_NAME_LENGTH as u16)
+    .required(true)
+}
+
+fn module_shared_option() -> CreateCommandOption {
+    CreateCommandOption::new(
+        CommandOptionType::Boolean,
+        "shared",
+        "Whether the module is shared with everyone in this server",
+    )
+}
+
+/// Lists the names of `modules` with their number of lines.
+fn module_list(modules: &[MathModule]) -> String {
+    if modules.is_empty() {
+        return String::from("None yet, create one with `/math module save`");
+    }
+
+    let mut list = String::new();
+
+    for module in modules {
+        let lines = module.code.lines().count();
+        let line = format!(
+            "`{}` ({lines} {})\n",
+            module.name,
+            if lines == 1 { "line" } else { "lines" }
+        );
+
+        if list.len() + line.len() > MAX_MODULE_LIST_LENGTH {
+            list.push_str("...\n");
+            break;
+        }
+
+        list.push_str(&line);
+    }
+
+    list
 }
@@ -1,36 +1,178 @@
 use std::sync::Arc;
 
 use anyhow::anyhow;
-use serenity::all::{CommandInteraction, CreateCommand};
+use serenity::all::{
+    Color, CommandInteraction, CreateCommand, CreateEmbed, CreateInteractionResponse,
+    CreateInteractionResponseFollowup, CreateInteractionResponseMessage,
+};
 
 use crate::{AppState, error::Error};
 
 mod ai;
 mod code;
+mod convert;
+mod economy;
+mod gamble;
 mod math;
+pub mod options;
+mod run_code;
 
 pub trait CommandHandler {
+    /// Name the command is registered and dispatched under.
+    const NAME: &'static str;
+
     async fn handle_command(
         interaction: CommandInteraction,
         state: Arc<AppState>,
     ) -> Result<(), Error>;
     fn command() -> CreateCommand;
+
+    /// Responds to autocomplete requests for options created with `set_autocomplete(true)`.
+    async fn autocomplete(
+        _interaction: CommandInteraction,
+        _state: Arc<AppState>,
+    ) -> Result<(), Error> {
+        Err(anyhow!("Command does not support autocomplete"))
+    }
 }
 
-pub async fn handle_interaction(
-    interaction: CommandInteraction,
-    state: Arc<AppState>,
+/// Responds with an error message that is only visible to the invoking user.
+async fn respond_error(
+    interaction: &CommandInteraction,
+    state: &Arc<AppState>,
+    message: &str,
 ) -> Result<(), Error> {
-    println!("{:?}", interaction.data);
+    interaction
+        .create_response(
+            &state.serenity_http,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .embed(CreateEmbed::new().color(Color::RED).description(message))
+                    .ephemeral(true),
+            ),
+        )
+        .await?;
 
-    match interaction.data.name.as_str() {
-        "math" => MathCommand::handle_command(interaction.clone(), state.clone()).await,
-        "ai" => AiCommand::handle_command(interaction.clone(), state.clone()).await,
-        "code" => CodeCommand::handle_command(interaction.clone(), state.clone()).await,
-        name => Err(anyhow!("Command with name '{}' not found", name)),
-    }
+    Ok(())
+}
+
+/// Like [`respond_error`] for interactions that were already deferred, the message is visible to
+/// everyone like the deferred response.
+async fn followup_error(
+    interaction: &CommandInteraction,
+    state: &Arc<AppState>,
+    message: &str,
+) -> Result<(), Error> {
+    interaction
+        .create_followup(
+            &state.serenity_http,
+            CreateInteractionResponseFollowup::new()
+                .embed(CreateEmbed::new().color(Color::RED).description(message)),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Generates the command list used for registration together with the dispatch functions, so a
+/// command can not be registered without being handled or the other way around.
+macro_rules! command_registry {
+    ($($handler:ident),* $(,)?) => {
+        /// Names of all commands that have a handler.
+        #[cfg_attr(not(test), allow(dead_code))]
+        pub const NAMES: &[&str] = &[$($handler::NAME),*];
+
+        /// All commands to register with the Discord API.
+        pub fn commands() -> Vec<CreateCommand> {
+            vec![$($handler::command()),*]
+        }
+
+        pub async fn handle_interaction(
+            interaction: CommandInteraction,
+            state: Arc<AppState>,
+        ) -> Result<(), Error> {
+            $(
+                if interaction.data.name == $handler::NAME {
+                    return $handler::handle_command(interaction, state).await;
+                }
+            )*
+
+            Err(anyhow!("Command with name '{}' not found", interaction.data.name))
+        }
+
+        pub async fn handle_autocomplete(
+            interaction: CommandInteraction,
+            state: Arc<AppState>,
+        ) -> Result<(), Error> {
+            $(
+                if interaction.data.name == $handler::NAME {
+                    return $handler::autocomplete(interaction, state).await;
+                }
+            )*
+
+            Err(anyhow!("Command with name '{}' not found", interaction.data.name))
+        }
+    };
 }
 
+command_registry!(
+    MathCommand,
+    CodeCommand,
+    ConvertCommand,
+    AiCommand,
+    EconomyCommand,
+    GambleCommand,
+    RunCodeCommand,
+);
+
 pub use ai::AiCommand;
 pub use code::CodeCommand;
+pub use convert::ConvertCommand;
+pub use economy::EconomyCommand;
+pub use gamble::GambleCommand;
 pub use math::MathCommand;
+pub use run_code::RunCodeCommand;
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn registered_names() -> Vec<String> {
+        commands()
+            .iter()
+            .map(|command| {
+                serde_json::to_value(command).unwrap()["name"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn registered_commands_have_handlers() {
+        for name in registered_names() {
+            assert!(NAMES.contains(&name.as_str()), "no handler for '{name}'");
+        }
+    }
+
+    #[test]
+    fn handlers_are_registered() {
+        let registered = registered_names();
+
+        for name in NAMES {
+            assert!(
+                registered.contains(&name.to_string()),
+                "'{name}' is not registered"
+            );
+        }
+    }
+
+    #[test]
+    fn command_names_are_unique() {
+        let names: HashSet<_> = NAMES.iter().collect();
+        assert_eq!(names.len(), NAMES.len());
+    }
+}
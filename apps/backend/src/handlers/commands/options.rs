@@ -0,0 +1,218 @@
+use serenity::all::{Attachment, CommandInteraction, ResolvedOption, ResolvedValue, User};
+
+#[derive(thiserror::Error, Debug)]
+pub enum OptionError {
+    #[error("missing required option `{0}`")]
+    Missing(String),
+
+    #[error("option `{name}` must be {expected}")]
+    InvalidType {
+        name: String,
+        expected: &'static str,
+    },
+
+    #[error("missing subcommand")]
+    MissingSubcommand,
+
+    #[error("unknown subcommand `{0}`")]
+    UnknownSubcommand(String),
+}
+
+/// A value that can be taken out of a single resolved command option.
+pub trait FromResolvedValue: Sized {
+    /// Describes the expected value in error messages, e.g. "a string".
+    const EXPECTED: &'static str;
+
+    fn from_resolved_value(value: &ResolvedValue<'_>) -> Option<Self>;
+}
+
+impl FromResolvedValue for String {
+    const EXPECTED: &'static str = "a string";
+
+    fn from_resolved_value(value: &ResolvedValue<'_>) -> Option<Self> {
+        match value {
+            ResolvedValue::String(value) => Some(value.to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl FromResolvedValue for i64 {
+    const EXPECTED: &'static str = "an integer";
+
+    fn from_resolved_value(value: &ResolvedValue<'_>) -> Option<Self> {
+        match value {
+            ResolvedValue::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FromResolvedValue for f64 {
+    const EXPECTED: &'static str = "a number";
+
+    fn from_resolved_value(value: &ResolvedValue<'_>) -> Option<Self> {
+        match value {
+            ResolvedValue::Number(value) => Some(*value),
+            ResolvedValue::Integer(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+}
+
+impl FromResolvedValue for bool {
+    const EXPECTED: &'static str = "a boolean";
+
+    fn from_resolved_value(value: &ResolvedValue<'_>) -> Option<Self> {
+        match value {
+            ResolvedValue::Boolean(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FromResolvedValue for Attachment {
+    const EXPECTED: &'static str = "an attachment";
+
+    fn from_resolved_value(value: &ResolvedValue<'_>) -> Option<Self> {
+        match value {
+            ResolvedValue::Attachment(attachment) => Some((*attachment).clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FromResolvedValue for User {
+    const EXPECTED: &'static str = "a user";
+
+    fn from_resolved_value(value: &ResolvedValue<'_>) -> Option<Self> {
+        match value {
+            ResolvedValue::User(user, _) => Some((*user).clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Options of a command or subcommand, looked up by name instead of position.
+pub struct Options<'a> {
+    options: Vec<ResolvedOption<'a>>,
+}
+
+impl<'a> Options<'a> {
+    pub fn new(options: Vec<ResolvedOption<'a>>) -> Self {
+        Self { options }
+    }
+
+    pub fn get<T: FromResolvedValue>(&self, name: &str) -> Result<T, OptionError> {
+        self.get_optional(name)?
+            .ok_or_else(|| OptionError::Missing(name.to_string()))
+    }
+
+    pub fn get_optional<T: FromResolvedValue>(&self, name: &str) -> Result<Option<T>, OptionError> {
+        let Some(option) = self.options.iter().find(|option| option.name == name) else {
+            return Ok(None);
+        };
+
+        T::from_resolved_value(&option.value)
+            .map(Some)
+            .ok_or_else(|| OptionError::InvalidType {
+                name: name.to_string(),
+                expected: T::EXPECTED,
+            })
+    }
+
+    /// Returns the name and options of the invoked subcommand or subcommand group.
+    pub fn subcommand(&self) -> Result<(&'a str, Options<'a>), OptionError> {
+        self.options
+            .iter()
+            .find_map(|option| match &option.value {
+                ResolvedValue::SubCommand(options) | ResolvedValue::SubCommandGroup(options) => {
+                    Some((option.name, Options::new(options.clone())))
+                }
+                _ => None,
+            })
+            .ok_or(OptionError::MissingSubcommand)
+    }
+}
+
+/// Maps the options of a command interaction onto a Rust type.
+pub trait FromResolvedOptions: Sized {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError>;
+
+    fn from_interaction(interaction: &CommandInteraction) -> Result<Self, OptionError> {
+        Self::from_options(&Options::new(interaction.data.options()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::all::CommandData;
+
+    use super::*;
+
+    fn command_data(options: serde_json::Value) -> CommandData {
+        serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "name": "test",
+            "type": 1,
+            "options": options,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn get_by_name() {
+        let data = command_data(serde_json::json!([
+            { "name": "count", "type": 4, "value": 3 },
+            { "name": "text", "type": 3, "value": "hello" },
+        ]));
+        let options = Options::new(data.options());
+
+        assert_eq!(options.get::<String>("text").unwrap(), "hello");
+        assert_eq!(options.get::<i64>("count").unwrap(), 3);
+    }
+
+    #[test]
+    fn missing_option() {
+        let data = command_data(serde_json::json!([]));
+        let options = Options::new(data.options());
+
+        assert!(matches!(
+            options.get::<String>("text"),
+            Err(OptionError::Missing(name)) if name == "text"
+        ));
+        assert!(options.get_optional::<String>("text").unwrap().is_none());
+    }
+
+    #[test]
+    fn invalid_type() {
+        let data = command_data(serde_json::json!([
+            { "name": "text", "type": 3, "value": "hello" },
+        ]));
+        let options = Options::new(data.options());
+
+        assert!(matches!(
+            options.get::<bool>("text"),
+            Err(OptionError::InvalidType {
+                expected: "a boolean",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn subcommand() {
+        let data = command_data(serde_json::json!([
+            {
+                "name": "sub",
+                "type": 1,
+                "options": [{ "name": "text", "type": 3, "value": "hello" }],
+            },
+        ]));
+        let options = Options::new(data.options());
+        let (name, options) = options.subcommand().unwrap();
+
+        assert_eq!(name, "sub");
+        assert_eq!(options.get::<String>("text").unwrap(), "hello");
+    }
+}
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use serenity::all::{
+    CommandInteraction, CommandType, CreateCommand, InstallationContext, InteractionContext,
+    ResolvedTarget,
+};
+
+use crate::{
+    AppState, error::Error, handlers::components::CodeSnippetComponent,
+    models::database::snippets::SnippetSource, services::code,
+};
+
+use super::{CommandHandler, followup_error, respond_error};
+
+/// Message context menu command running the first code block of a message.
+pub struct RunCodeCommand;
+
+impl CommandHandler for RunCodeCommand {
+    const NAME: &'static str = "Run Code";
+
+    async fn handle_command(
+        interaction: CommandInteraction,
+        state: Arc<AppState>,
+    ) -> Result<(), Error> {
+        let Some(ResolvedTarget::Message(message)) = interaction.data.target() else {
+            return respond_error(&interaction, &state, "This command only works on messages")
+                .await;
+        };
+
+        let blocks = code::extract_code_blocks(&message.content);
+
+        if blocks.is_empty() {
+            return respond_error(&interaction, &state, "This message has no code blocks").await;
+        }
+
+        // Loading the languages can take longer than the 3 seconds to respond
+        interaction.defer(&state.serenity_http).await?;
+
+        state.languages.ensure_loaded(&state.code_executor).await?;
+
+        // Run the first block in a language the execution service knows about
+        let Some((language, block)) = blocks.iter().find_map(|block| {
+            let language = state.languages.resolve(block.language.as_deref()?)?;
+            Some((language, block))
+        }) else {
+            return followup_error(
+                &interaction,
+                &state,
+                "None of the code blocks has a supported language, tag the block like ```py",
+            )
+            .await;
+        };
+
+        let source = SnippetSource {
+            language: language.language,
+            version: language.version,
+            code: block.code.clone(),
+            ..Default::default()
+        };
+
+        let followup = CodeSnippetComponent::run(&state, interaction.user.id, source).await?;
+
+        interaction
+            .create_followup(&state.serenity_http, followup)
+            .await?;
+
+        Ok(())
+    }
+
+    fn command() -> CreateCommand {
+        CreateCommand::new(Self::NAME)
+            .kind(CommandType::Message)
+            .integration_types(vec![InstallationContext::Guild, InstallationContext::User])
+            .contexts(vec![
+                InteractionContext::Guild,
+                InteractionContext::BotDm,
+                InteractionContext::PrivateChannel,
+            ])
+    }
+}
@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use serenity::all::{
+    ButtonStyle, ComponentInteraction, CreateActionRow, CreateButton, CreateInteractionResponse,
+};
+
+use crate::{
+    AppState,
+    error::Error,
+    handlers::modals::{AiReplyModal, ModalHandler},
+    models::custom_id::CustomId,
+};
+
+use super::ComponentHandler;
+
+/// "Continue" button on AI responses, asking for the next prompt of the conversation.
+pub struct AiConversationComponent;
+
+impl ComponentHandler for AiConversationComponent {
+    async fn handle_component(
+        interaction: ComponentInteraction,
+        state: Arc<AppState>,
+    ) -> Result<(), Error> {
+        let custom_id = CustomId::try_from(interaction.data.custom_id.clone())?;
+
+        let [conversation_id] = custom_id.data.as_slice() else {
+            return Err(anyhow!("Failed to get conversation from custom id"));
+        };
+
+        interaction
+            .create_response(
+                &state.serenity_http,
+                CreateInteractionResponse::Modal(AiReplyModal::modal(Some(vec![
+                    conversation_id.clone(),
+                ]))?),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// `data` is required and must contain the ID of the conversation.
+    fn action_row(data: Option<Vec<String>>) -> CreateActionRow {
+        let custom_id = CustomId::new("ai_conversation")
+            .data(data.unwrap())
+            .try_to_string()
+            .unwrap();
+
+        CreateActionRow::Buttons(vec![
+            CreateButton::new(custom_id)
+                .label("Continue")
+                .style(ButtonStyle::Primary),
+        ])
+    }
+}
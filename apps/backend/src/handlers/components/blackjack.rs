@@ -0,0 +1,160 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use serenity::all::{
+    ButtonStyle, Color, ComponentInteraction, CreateActionRow, CreateButton, CreateEmbed,
+    CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage,
+};
+
+use crate::{
+    AppState,
+    error::Error,
+    games::blackjack::{Blackjack, Card, Outcome, hand_value},
+    models::{custom_id::CustomId, database::gambling::GamblingRepository},
+};
+
+use super::{ComponentHandler, respond_ephemeral};
+
+pub struct BlackjackComponent;
+
+impl ComponentHandler for BlackjackComponent {
+    async fn handle_component(
+        interaction: ComponentInteraction,
+        state: Arc<AppState>,
+    ) -> Result<(), Error> {
+        let custom_id = CustomId::try_from(interaction.data.custom_id.clone())?;
+
+        let [game_id, action] = custom_id.data.as_slice() else {
+            return Err(anyhow!("Failed to get game and action from custom id"));
+        };
+
+        let game = state
+            .database
+            .get_blackjack_game(game_id.parse()?)
+            .await?
+            .ok_or(anyhow!("Blackjack game {game_id} not found"))?;
+
+        if game.user_id() != interaction.user.id {
+            return respond_ephemeral(&interaction, &state, "This is not your game").await;
+        }
+
+        let mut blackjack = game.state.0.clone();
+
+        match action.as_str() {
+            "hit" => blackjack.hit(),
+            "stand" => blackjack.stand(),
+            action => return Err(anyhow!("Invalid blackjack action '{action}'")),
+        }
+
+        if !state
+            .database
+            .update_blackjack_game(&game, &blackjack)
+            .await?
+        {
+            return respond_ephemeral(&interaction, &state, "This game was already updated").await;
+        }
+
+        interaction
+            .create_response(
+                &state.serenity_http,
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .embed(Self::embed(&blackjack))
+                        .components(Self::components(game.id, &blackjack)),
+                ),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// `data` is required and must contain the game ID.
+    fn action_row(data: Option<Vec<String>>) -> CreateActionRow {
+        let data = data.unwrap();
+
+        let button = |action: &str| {
+            CustomId::new("blackjack")
+                .data(data.clone())
+                .add_data(action)
+                .try_to_string()
+                .unwrap()
+        };
+
+        CreateActionRow::Buttons(vec![
+            CreateButton::new(button("hit"))
+                .label("Hit")
+                .style(ButtonStyle::Primary),
+            CreateButton::new(button("stand"))
+                .label("Stand")
+                .style(ButtonStyle::Secondary),
+        ])
+    }
+}
+
+impl BlackjackComponent {
+    pub fn embed(game: &Blackjack) -> CreateEmbed {
+        let format_hand = |cards: &[Card]| {
+            cards
+                .iter()
+                .map(Card::to_string)
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        // The second card of the dealer stays hidden until the game is over
+        let dealer = if game.is_finished() {
+            format!(
+                "{} ({})",
+                format_hand(&game.dealer),
+                hand_value(&game.dealer)
+            )
+        } else {
+            format!("{} ??", format_hand(&game.dealer[..1]))
+        };
+
+        // Payouts include the returned bet, only the winnings are shown
+        let (color, description) = match game.outcome() {
+            None => (Color::BLURPLE, String::from("Hit or stand?")),
+            Some(Outcome::Blackjack) => (
+                Color::FOOYOO,
+                format!(
+                    "Blackjack! You won **{}** coins",
+                    Outcome::Blackjack.payout(game.bet) - game.bet
+                ),
+            ),
+            Some(Outcome::Win) => (
+                Color::FOOYOO,
+                format!(
+                    "You won **{}** coins",
+                    Outcome::Win.payout(game.bet) - game.bet
+                ),
+            ),
+            Some(Outcome::Push) => (Color::GOLD, String::from("Push, your bet was returned")),
+            Some(Outcome::Lose) => (Color::RED, String::from("You lost")),
+        };
+
+        CreateEmbed::new()
+            .color(color)
+            .title("Blackjack")
+            .description(description)
+            .field(
+                "Your hand",
+                format!(
+                    "{} ({})",
+                    format_hand(&game.player),
+                    hand_value(&game.player)
+                ),
+                true,
+            )
+            .field("Dealer", dealer, true)
+            .footer(CreateEmbedFooter::new(format!("Bet: {} coins", game.bet)))
+    }
+
+    pub fn components(game_id: i64, game: &Blackjack) -> Vec<CreateActionRow> {
+        if game.is_finished() {
+            vec![]
+        } else {
+            vec![Self::action_row(Some(vec![game_id.to_string()]))]
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use serenity::all::{
+    ActionRow, ActionRowComponent, ButtonKind, ComponentInteraction, CreateActionRow, CreateButton,
+    CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage,
+};
+
+use crate::{
+    AppState,
+    error::Error,
+    models::custom_id::CustomId,
+    services::code::{self, Stream},
+};
+
+use super::ComponentHandler;
+
+/// Page buttons of long code output. The pages are read back from the attached output file,
+/// so nothing has to be stored for them.
+pub struct CodeOutputComponent;
+
+impl ComponentHandler for CodeOutputComponent {
+    async fn handle_component(
+        interaction: ComponentInteraction,
+        state: Arc<AppState>,
+    ) -> Result<(), Error> {
+        let custom_id = CustomId::try_from(interaction.data.custom_id.clone())?;
+
+        let [stream, page] = custom_id.data.as_slice() else {
+            return Err(anyhow!("Failed to get stream and page from custom id"));
+        };
+
+        let stream = Stream::from_name(stream).ok_or(anyhow!("Invalid stream '{stream}'"))?;
+
+        let attachment = interaction
+            .message
+            .attachments
+            .iter()
+            .find(|attachment| attachment.filename == stream.file_name())
+            .ok_or(anyhow!("Failed to get {} attachment", stream.file_name()))?;
+
+        let text = state
+            .http_client
+            .get(&attachment.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        let pages = code::paginate(&text);
+        let page = page.parse::<usize>()?.min(pages.len() - 1);
+
+        let embed = interaction
+            .message
+            .embeds
+            .first()
+            .cloned()
+            .map(CreateEmbed::from)
+            .ok_or(anyhow!("Failed to get output embed"))?
+            .description(code::page_description(&pages, page));
+
+        interaction
+            .create_response(
+                &state.serenity_http,
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .embed(embed)
+                        .components(Self::replace_page_buttons(
+                            &interaction.message.components,
+                            code::page_buttons(stream, page, pages.len()),
+                        )),
+                ),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// `data` is required and must contain the stream, page and page count.
+    fn action_row(data: Option<Vec<String>>) -> CreateActionRow {
+        let data = data.unwrap();
+
+        code::page_buttons(
+            Stream::from_name(&data[0]).unwrap(),
+            data[1].parse().unwrap(),
+            data[2].parse().unwrap(),
+        )
+    }
+}
+
+impl CodeOutputComponent {
+    /// Rebuilds the components of a message with `page_buttons` in place of its current page
+    /// buttons, keeping the other buttons like "Run again".
+    fn replace_page_buttons(
+        rows: &[ActionRow],
+        page_buttons: CreateActionRow,
+    ) -> Vec<CreateActionRow> {
+        let mut page_buttons = Some(page_buttons);
+
+        rows.iter()
+            .filter_map(|row| {
+                let buttons: Vec<_> = row
+                    .components
+                    .iter()
+                    .filter_map(|component| match component {
+                        ActionRowComponent::Button(button) => Some(button.clone()),
+                        _ => None,
+                    })
+                    .collect();
+
+                let is_pager = buttons.iter().any(|button| match &button.data {
+                    ButtonKind::NonLink { custom_id, .. } => CustomId::try_from(custom_id.clone())
+                        .is_ok_and(|custom_id| custom_id.id == "code_output"),
+                    _ => false,
+                });
+
+                if is_pager {
+                    page_buttons.take()
+                } else {
+                    Some(CreateActionRow::Buttons(
+                        buttons.into_iter().map(CreateButton::from).collect(),
+                    ))
+                }
+            })
+            .collect()
+    }
+}
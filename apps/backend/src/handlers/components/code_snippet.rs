@@ -0,0 +1,172 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use serenity::all::{
+    ButtonStyle, ComponentInteraction, CreateActionRow, CreateButton, CreateInteractionResponse,
+    CreateInteractionResponseFollowup, UserId,
+};
+
+use crate::{
+    AppState,
+    error::Error,
+    handlers::modals::CodeModal,
+    models::{
+        api::code::{ExecuteFile, ExecuteRequest},
+        custom_id::CustomId,
+        database::snippets::{SnippetRepository, SnippetSource},
+    },
+    services::code::{self, CodeExecutor, LimitPolicy, RequestedLimits},
+};
+
+use super::{ComponentHandler, respond_ephemeral};
+
+/// Maximum length of a prefilled modal input accepted by Discord.
+const MAX_INPUT_LENGTH: usize = 4000;
+
+/// "Edit & re-run" and "Run again" buttons on code results.
+pub struct CodeSnippetComponent;
+
+impl ComponentHandler for CodeSnippetComponent {
+    async fn handle_component(
+        interaction: ComponentInteraction,
+        state: Arc<AppState>,
+    ) -> Result<(), Error> {
+        let custom_id = CustomId::try_from(interaction.data.custom_id.clone())?;
+
+        let [snippet_id, action] = custom_id.data.as_slice() else {
+            return Err(anyhow!("Failed to get snippet and action from custom id"));
+        };
+
+        let Some(snippet) = state.database.get_snippet(snippet_id.parse()?).await? else {
+            return respond_ephemeral(
+                &interaction,
+                &state,
+                "This code is no longer stored, run it with `/code` again",
+            )
+            .await;
+        };
+
+        // Snippets can contain files and input the author did not post in the channel
+        if snippet.user_id() != interaction.user.id {
+            return respond_ephemeral(
+                &interaction,
+                &state,
+                "Only the author of this code can edit or run it again",
+            )
+            .await;
+        }
+
+        match action.as_str() {
+            "edit" => {
+                let source = &snippet.source;
+
+                let too_long = [Some(&source.code), source.stdin.as_ref()]
+                    .into_iter()
+                    .flatten()
+                    .any(|input| input.chars().count() > MAX_INPUT_LENGTH);
+
+                if too_long {
+                    return respond_ephemeral(
+                        &interaction,
+                        &state,
+                        "This code is too long to be edited in Discord",
+                    )
+                    .await;
+                }
+
+                interaction
+                    .create_response(
+                        &state.serenity_http,
+                        CreateInteractionResponse::Modal(CodeModal::prefilled(&snippet)?),
+                    )
+                    .await?;
+            }
+            "run" => {
+                interaction.defer(&state.serenity_http).await?;
+
+                let followup = Self::execute(&state, snippet.id, &snippet.source).await?;
+
+                interaction
+                    .create_followup(&state.serenity_http, followup)
+                    .await?;
+            }
+            action => return Err(anyhow!("Invalid snippet action '{action}'")),
+        }
+
+        Ok(())
+    }
+
+    /// `data` is required and must contain the snippet ID.
+    fn action_row(data: Option<Vec<String>>) -> CreateActionRow {
+        let data = data.unwrap();
+
+        let button = |action: &str| {
+            CustomId::new("code_snippet")
+                .data(data.clone())
+                .add_data(action)
+                .try_to_string()
+                .unwrap()
+        };
+
+        CreateActionRow::Buttons(vec![
+            CreateButton::new(button("edit"))
+                .label("Edit & re-run")
+                .style(ButtonStyle::Primary),
+            CreateButton::new(button("run"))
+                .label("Run again")
+                .style(ButtonStyle::Secondary),
+        ])
+    }
+}
+
+impl CodeSnippetComponent {
+    /// Stores `source` as a snippet of `user_id` and runs it, returning the rendered result with
+    /// buttons to edit and run it again.
+    pub async fn run(
+        state: &AppState,
+        user_id: UserId,
+        source: SnippetSource,
+    ) -> Result<CreateInteractionResponseFollowup, Error> {
+        let snippet_id = state.database.create_snippet(user_id, &source).await?;
+
+        Self::execute(state, snippet_id, &source).await
+    }
+
+    async fn execute(
+        state: &AppState,
+        snippet_id: i64,
+        source: &SnippetSource,
+    ) -> Result<CreateInteractionResponseFollowup, Error> {
+        let mut main = ExecuteFile::new().content(&source.code);
+
+        if let Some(name) = &source.name {
+            main = main.name(name);
+        }
+
+        let mut request = code::request_file(&source.language, main)
+            .version(&source.version)
+            .args(source.args.0.clone());
+
+        request = source
+            .files
+            .iter()
+            .cloned()
+            .fold(request, ExecuteRequest::add_file);
+
+        if let Some(stdin) = &source.stdin {
+            request = request.stdin(stdin);
+        }
+
+        let response = state
+            .code_executor
+            .execute(&LimitPolicy::DISCORD.apply(request, &RequestedLimits::default()))
+            .await?;
+
+        let mut output = code::render_output(&response);
+        output
+            .components
+            .push(Self::action_row(Some(vec![snippet_id.to_string()])));
+
+        Ok(output.followup())
+    }
+}
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use crate::{AppState, error::Error, models::custom_id::CustomId};
+use anyhow::anyhow;
+use serenity::all::{
+    ComponentInteraction, CreateActionRow, CreateInteractionResponse,
+    CreateInteractionResponseMessage,
+};
+
+mod ai_conversation;
+mod blackjack;
+mod code_output;
+mod code_snippet;
+
+pub trait ComponentHandler {
+    async fn handle_component(
+        interaction: ComponentInteraction,
+        state: Arc<AppState>,
+    ) -> Result<(), Error>;
+    fn action_row(data: Option<Vec<String>>) -> CreateActionRow;
+}
+
+/// Responds with a message that is only visible to the invoking user.
+async fn respond_ephemeral(
+    interaction: &ComponentInteraction,
+    state: &Arc<AppState>,
+    message: &str,
+) -> Result<(), Error> {
+    interaction
+        .create_response(
+            &state.serenity_http,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(message)
+                    .ephemeral(true),
+            ),
+        )
+        .await?;
+
+    Ok(())
+}
+
+pub async fn handle_interaction(
+    interaction: ComponentInteraction,
+    state: Arc<AppState>,
+) -> Result<(), Error> {
+    let custom_id = CustomId::try_from(interaction.data.custom_id.clone())?;
+
+    match custom_id.id.as_ref() {
+        "ai_conversation" => {
+            AiConversationComponent::handle_component(interaction.clone(), state.clone()).await
+        }
+        "blackjack" => {
+            BlackjackComponent::handle_component(interaction.clone(), state.clone()).await
+        }
+        "code_output" => {
+            CodeOutputComponent::handle_component(interaction.clone(), state.clone()).await
+        }
+        "code_snippet" => {
+            CodeSnippetComponent::handle_component(interaction.clone(), state.clone()).await
+        }
+        name => Err(anyhow!("Component with ID '{}' not found", name)),
+    }
+}
+
+pub use ai_conversation::AiConversationComponent;
+pub use blackjack::BlackjackComponent;
+pub use code_output::CodeOutputComponent;
+pub use code_snippet::CodeSnippetComponent;
@@ -1,2 +1,3 @@
 pub mod commands;
+pub mod components;
 pub mod modals;
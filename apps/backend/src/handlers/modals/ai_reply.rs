@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use serenity::all::{
+    ActionRowComponent, CreateActionRow, CreateInputText, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateModal, InputText, InputTextStyle, ModalInteraction,
+};
+
+use crate::{
+    AppState,
+    error::Error,
+    handlers::commands::AiCommand,
+    models::{custom_id::CustomId, database::conversations::ConversationRepository},
+};
+
+use super::ModalHandler;
+
+/// Next prompt of an AI conversation, opened by its "Continue" button.
+pub struct AiReplyModal;
+
+impl ModalHandler for AiReplyModal {
+    async fn handle_modal(
+        interaction: ModalInteraction,
+        state: Arc<AppState>,
+    ) -> Result<(), Error> {
+        let custom_id = CustomId::try_from(interaction.data.custom_id.clone())?;
+
+        let [conversation_id] = custom_id.data.as_slice() else {
+            return Err(anyhow!("Failed to get conversation from custom id"));
+        };
+
+        let prompt = interaction
+            .data
+            .components
+            .iter()
+            .flat_map(|row| &row.components)
+            .find_map(|component| match component {
+                ActionRowComponent::InputText(InputText {
+                    custom_id,
+                    value: Some(value),
+                    ..
+                }) if custom_id == "prompt" => Some(value.clone()),
+                _ => None,
+            })
+            .ok_or(anyhow!("Failed to get prompt input"))?;
+
+        let conversation = state
+            .database
+            .get_conversation(conversation_id.parse()?)
+            .await?
+            .ok_or(anyhow!("Conversation {conversation_id} not found"))?;
+
+        // Deferred as a new message, as `defer` would edit the message with the button instead
+        interaction
+            .create_response(
+                &state.serenity_http,
+                CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new()),
+            )
+            .await?;
+
+        AiCommand::reply(&state, &interaction.token, &conversation, &prompt).await
+    }
+
+    /// `data` is required and must contain the ID of the conversation.
+    fn modal(data: Option<Vec<String>>) -> Result<CreateModal, Error> {
+        let modal = CreateModal::new(
+            CustomId::new("ai_reply")
+                .data(data.unwrap())
+                .try_to_string()?,
+            "Continue the conversation",
+        )
+        .components(vec![CreateActionRow::InputText(
+            CreateInputText::new(InputTextStyle::Paragraph, "Prompt", "prompt").required(true),
+        )]);
+
+        Ok(modal)
+    }
+}
@@ -2,18 +2,20 @@ use std::sync::Arc;
 
 use anyhow::anyhow;
 use serenity::all::{
-    ActionRowComponent, Color, CreateActionRow, CreateEmbed, CreateEmbedFooter, CreateInputText,
-    CreateInteractionResponseFollowup, CreateModal, InputText, InputTextStyle, ModalInteraction,
+    ActionRowComponent, CreateActionRow, CreateInputText, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateModal, InputText, InputTextStyle, ModalInteraction,
 };
+use sqlx::types::Json;
 
 use crate::{
     AppState,
-    env::ENV,
     error::Error,
+    handlers::components::CodeSnippetComponent,
     models::{
-        api::code::{ExecuteFile, ExecuteRequest, ExecuteResponse},
         custom_id::CustomId,
+        database::snippets::{Snippet, SnippetRepository, SnippetSource},
     },
+    services::code,
 };
 
 use super::ModalHandler;
@@ -25,111 +27,152 @@ impl ModalHandler for CodeModal {
         interaction: ModalInteraction,
         state: Arc<AppState>,
     ) -> Result<(), Error> {
-        let language = CustomId::try_from(interaction.data.custom_id.clone())?
-            .data
-            .first()
-            .ok_or(anyhow!("Failed to get language from custom id"))?
-            .clone();
-
-        let ActionRowComponent::InputText(InputText {
-            value: Some(code), ..
-        }) = interaction
-            .data
-            .components
-            .first()
-            .ok_or(anyhow!("Failed to get code action row"))?
-            .components
-            .first()
-            .ok_or(anyhow!("Failed to get code component"))?
-        else {
-            return Err(anyhow!("Failed to get code input"));
+        let custom_id = CustomId::try_from(interaction.data.custom_id.clone())?;
+
+        // Edited snippets reference the original snippet to keep its file name and additional
+        // files
+        let (language, version, name, files) = match custom_id.data.as_slice() {
+            [language, version] => (language, version, None, vec![]),
+            [language, version, snippet_id] => {
+                let snippet = state
+                    .database
+                    .get_snippet(snippet_id.parse()?)
+                    .await?
+                    .ok_or(anyhow!("Snippet {snippet_id} not found"))?;
+
+                // Only the author gets the modal from the edit button, but the custom id is sent
+                // back by the client
+                if snippet.user_id() != interaction.user.id {
+                    interaction
+                        .create_response(
+                            &state.serenity_http,
+                            CreateInteractionResponse::Message(
+                                CreateInteractionResponseMessage::new()
+                                    .content("Only the author of this code can edit it")
+                                    .ephemeral(true),
+                            ),
+                        )
+                        .await?;
+
+                    return Ok(());
+                }
+
+                (
+                    language,
+                    version,
+                    snippet.source.name,
+                    snippet.source.files.0,
+                )
+            }
+            _ => return Err(anyhow!("Failed to get language and version from custom id")),
+        };
+
+        let code = input_value(&interaction, "code").ok_or(anyhow!("Failed to get code input"))?;
+
+        let source = SnippetSource {
+            language: language.clone(),
+            version: version.clone(),
+            code: code.to_string(),
+            name,
+            stdin: input_value(&interaction, "stdin").map(String::from),
+            args: Json(
+                input_value(&interaction, "args")
+                    .map(code::parse_args)
+                    .unwrap_or_default(),
+            ),
+            files: Json(files),
         };
 
         interaction.defer(&state.serenity_http).await?;
 
-        let response = state
-            .http_client
-            .post("https://v2-api.nigga.church/code/execute")
-            .header("Authorization", &ENV.code_token)
-            .json(
-                &ExecuteRequest::new()
-                    .language(language)
-                    .version("*")
-                    .add_file(ExecuteFile::new().content(code)),
-            )
-            .send()
-            .await?
-            .error_for_status()?;
-
-        let response = response.json::<ExecuteResponse>().await?;
-
-        if response
-            .compile
-            .as_ref()
-            .is_some_and(|s| s.code.is_some_and(|c| c != 0))
-        {
-            let embed = CreateEmbed::new()
-                .color(Color::RED)
-                .title("Compile Error")
-                .description(format!("```\n{}\n```", {
-                    let compile = response.compile.as_ref().unwrap();
-
-                    if compile.output.is_empty() {
-                        format!("[Exited with code {}]", compile.code.unwrap())
-                    } else {
-                        compile.output.clone()
-                    }
-                }))
-                .footer(CreateEmbedFooter::new(format!(
-                    "Language: {} {}",
-                    response.language, response.version
-                )));
-
-            interaction
-                .create_followup(
-                    &state.serenity_http,
-                    CreateInteractionResponseFollowup::new().add_embed(embed),
-                )
-                .await?;
-        } else {
-            let embed = CreateEmbed::new()
-                .color(Color::FOOYOO)
-                .title("Output")
-                .description(format!(
-                    "```\n{}\n```",
-                    if response.run.output.is_empty() {
-                        "No output, try logging the expression."
-                    } else {
-                        &response.run.output
-                    }
-                ))
-                .footer(CreateEmbedFooter::new(format!(
-                    "Language: {} {}",
-                    response.language, response.version
-                )));
-
-            interaction
-                .create_followup(
-                    &state.serenity_http,
-                    CreateInteractionResponseFollowup::new().add_embed(embed),
-                )
-                .await?;
-        }
+        let followup = CodeSnippetComponent::run(&state, interaction.user.id, source).await?;
+
+        interaction
+            .create_followup(&state.serenity_http, followup)
+            .await?;
 
         Ok(())
     }
 
-    /// `data` is required for this modal.
-    fn modal(data: Option<Vec<String>>) -> CreateModal {
-        CreateModal::new(
-            CustomId::new("code")
-                .data(data.unwrap())
-                .try_to_string()
-                .unwrap(),
+    /// `data` is required and must contain the language and version, optionally followed by
+    /// the ID of a snippet whose additional files are sent along.
+    fn modal(data: Option<Vec<String>>) -> Result<CreateModal, Error> {
+        Self::build(data.unwrap(), &SnippetSource::default())
+    }
+}
+
+impl CodeModal {
+    /// Modal with the inputs filled with the source of a previous execution.
+    pub fn prefilled(snippet: &Snippet) -> Result<CreateModal, Error> {
+        let source = &snippet.source;
+        let mut data = vec![source.language.clone(), source.version.clone()];
+
+        if !source.files.is_empty() {
+            data.push(snippet.id.to_string());
+        }
+
+        Self::build(data, source)
+    }
+
+    /// Fails if `data` does not fit into a custom id.
+    fn build(data: Vec<String>, source: &SnippetSource) -> Result<CreateModal, Error> {
+        let input = |style, label: &str, custom_id: &str, value: &str| {
+            let input = CreateInputText::new(style, label, custom_id);
+
+            if value.is_empty() {
+                input
+            } else {
+                input.value(value)
+            }
+        };
+
+        let modal = CreateModal::new(
+            CustomId::new("code").data(data).try_to_string()?,
             "Execute Code",
         )
-        .components(vec![CreateActionRow::InputText(
-            CreateInputText::new(InputTextStyle::Paragraph, "Code", "code").required(true),
-        )])
+        .components(vec![
+            CreateActionRow::InputText(
+                input(InputTextStyle::Paragraph, "Code", "code", &source.code).required(true),
+            ),
+            CreateActionRow::InputText(
+                input(
+                    InputTextStyle::Paragraph,
+                    "Input",
+                    "stdin",
+                    source.stdin.as_deref().unwrap_or_default(),
+                )
+                .placeholder("Passed to the program on stdin")
+                .required(false),
+            ),
+            CreateActionRow::InputText(
+                input(
+                    InputTextStyle::Short,
+                    "Arguments",
+                    "args",
+                    &code::format_args(&source.args),
+                )
+                .placeholder(r#"Separated by spaces, use "quotes" to keep spaces"#)
+                .required(false),
+            ),
+        ]);
+
+        Ok(modal)
     }
 }
+
+/// Returns the value of the input with `custom_id`, or `None` if it was left empty.
+fn input_value<'a>(interaction: &'a ModalInteraction, custom_id: &str) -> Option<&'a str> {
+    interaction
+        .data
+        .components
+        .iter()
+        .flat_map(|row| &row.components)
+        .find_map(|component| match component {
+            ActionRowComponent::InputText(InputText {
+                custom_id: id,
+                value: Some(value),
+                ..
+            }) if id == custom_id && !value.is_empty() => Some(value.as_str()),
+            _ => None,
+        })
+}
@@ -0,0 +1,246 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use serenity::all::{
+    ActionRowComponent, Color, CreateActionRow, CreateEmbed, CreateInputText,
+    CreateInteractionResponseFollowup, CreateModal, GuildId, InputText, InputTextStyle,
+    ModalInteraction, Permissions, UserId,
+};
+
+use crate::{
+    AppState,
+    error::Error,
+    math::{self, Modules},
+    models::{
+        custom_id::CustomId,
+        database::math_modules::{MathModule, MathModuleRepository, ModuleOwner},
+    },
+};
+
+use super::ModalHandler;
+
+/// Maximum number of modules a user or guild can save.
+pub const MAX_MODULES: usize = 25;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ModuleError {
+    #[error("{0}")]
+    InvalidName(String),
+
+    #[error("Shared modules only exist in servers")]
+    NotInGuild,
+
+    #[error("Only the author of `{0}` and members who can manage the server can change it")]
+    NotAllowed(String),
+
+    #[error("At most {MAX_MODULES} modules can be saved, delete one first")]
+    TooMany,
+
+    #[error(transparent)]
+    Other(#[from] Error),
+}
+
+pub struct MathModuleModal;
+
+impl ModalHandler for MathModuleModal {
+    async fn handle_modal(
+        interaction: ModalInteraction,
+        state: Arc<AppState>,
+    ) -> Result<(), Error> {
+        let custom_id = CustomId::try_from(interaction.data.custom_id.clone())?;
+
+        let [scope, name] = custom_id.data.as_slice() else {
+            return Err(anyhow!("Failed to get scope and name from custom id"));
+        };
+
+        let code = interaction
+            .data
+            .components
+            .iter()
+            .flat_map(|row| &row.components)
+            .find_map(|component| match component {
+                ActionRowComponent::InputText(InputText {
+                    custom_id,
+                    value: Some(value),
+                    ..
+                }) if custom_id == "code" => Some(value.clone()),
+                _ => None,
+            })
+            .ok_or(anyhow!("Failed to get code input"))?;
+
+        let user_id = interaction.user.id;
+        let permissions = interaction
+            .member
+            .as_ref()
+            .and_then(|member| member.permissions);
+
+        interaction.defer(&state.serenity_http).await?;
+
+        // Checked again as the module could have been changed since the modal was opened
+        let result = async {
+            let owner = Self::owner(user_id, interaction.guild_id, scope == "guild")?;
+            Self::authorize(&state, owner, name, user_id, permissions).await?;
+
+            let modules: Modules = state
+                .database
+                .importable_modules(user_id, interaction.guild_id)
+                .await?
+                .into_iter()
+                .collect();
+
+            Ok::<_, ModuleError>((owner, modules))
+        }
+        .await;
+
+        let (owner, modules) = match result {
+            Ok(result) => result,
+            Err(ModuleError::Other(error)) => return Err(error),
+            Err(error) => {
+                let embed = CreateEmbed::new()
+                    .color(Color::RED)
+                    .description(error.to_string());
+
+                interaction
+                    .create_followup(
+                        &state.serenity_http,
+                        CreateInteractionResponseFollowup::new().embed(embed),
+                    )
+                    .await?;
+
+                return Ok(());
+            }
+        };
+
+        let embed = match state
+            .math_sessions
+            .with_modules(modules)
+            .check_module(&code)
+            .await
+        {
+            Ok(()) => {
+                state
+                    .database
+                    .save_module(owner, user_id, name, &code)
+                    .await?;
+
+                let audience = match owner {
+                    ModuleOwner::User(_) => "you",
+                    ModuleOwner::Guild(_) => "everyone in this server",
+                };
+
+                CreateEmbed::new().color(Color::FOOYOO).description(format!(
+                    "Saved `{name}`, {audience} can import it with `use {name}`\n-# Sessions that already imported it keep the previous version until `/math reset`"
+                ))
+            }
+            Err(error) => CreateEmbed::new().color(Color::RED).description(format!(
+                "**Module `{name}` was not saved:**\n```{error}\n```"
+            )),
+        };
+
+        interaction
+            .create_followup(
+                &state.serenity_http,
+                CreateInteractionResponseFollowup::new().embed(embed),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// `data` is required and must contain the scope, `user` or `guild`, and the name of the
+    /// module.
+    fn modal(data: Option<Vec<String>>) -> Result<CreateModal, Error> {
+        Self::build(data.unwrap(), "")
+    }
+}
+
+impl MathModuleModal {
+    /// Modal to save the module `name` of `owner`, with the input filled with `code`.
+    pub fn prefilled(owner: ModuleOwner, name: &str, code: &str) -> Result<CreateModal, Error> {
+        let scope = match owner {
+            ModuleOwner::User(_) => "user",
+            ModuleOwner::Guild(_) => "guild",
+        };
+
+        Self::build(vec![scope.to_string(), name.to_string()], code)
+    }
+
+    fn build(data: Vec<String>, code: &str) -> Result<CreateModal, Error> {
+        let title = format!("Save {}", data[1]);
+
+        let mut input = CreateInputText::new(InputTextStyle::Paragraph, "Code", "code")
+            .placeholder("let speed_of_sound = 343 m/s")
+            .max_length(math::MAX_CODE_LENGTH as u16)
+            .required(true);
+
+        if !code.is_empty() {
+            input = input.value(code);
+        }
+
+        // Modal titles are limited to 45 characters
+        let modal = CreateModal::new(
+            CustomId::new("math_module").data(data).try_to_string()?,
+            title.chars().take(45).collect::<String>(),
+        )
+        .components(vec![CreateActionRow::InputText(input)]);
+
+        Ok(modal)
+    }
+
+    /// Returns who the modules of `user_id` are saved for, the guild if `shared`.
+    pub fn owner(
+        user_id: UserId,
+        guild_id: Option<GuildId>,
+        shared: bool,
+    ) -> Result<ModuleOwner, ModuleError> {
+        match (shared, guild_id) {
+            (false, _) => Ok(ModuleOwner::User(user_id)),
+            (true, Some(guild_id)) => Ok(ModuleOwner::Guild(guild_id)),
+            (true, None) => Err(ModuleError::NotInGuild),
+        }
+    }
+
+    /// Checks that `user_id` may save the module `name` of `owner`, returning the existing
+    /// module.
+    pub async fn authorize(
+        state: &AppState,
+        owner: ModuleOwner,
+        name: &str,
+        user_id: UserId,
+        permissions: Option<Permissions>,
+    ) -> Result<Option<MathModule>, ModuleError> {
+        math::validate_name(name).map_err(ModuleError::InvalidName)?;
+
+        let existing = state.database.get_module(owner, name).await?;
+
+        match &existing {
+            Some(module) if !Self::can_change(module, user_id, permissions) => {
+                return Err(ModuleError::NotAllowed(name.to_string()));
+            }
+            Some(_) => {}
+            None => {
+                if state.database.list_modules(owner).await?.len() >= MAX_MODULES {
+                    return Err(ModuleError::TooMany);
+                }
+            }
+        }
+
+        Ok(existing)
+    }
+
+    /// Whether `user_id` may replace or delete `module`. Modules of guilds can be changed by
+    /// their author and members who can manage the guild.
+    pub fn can_change(
+        module: &MathModule,
+        user_id: UserId,
+        permissions: Option<Permissions>,
+    ) -> bool {
+        match module.owner() {
+            ModuleOwner::User(owner_id) => owner_id == user_id,
+            ModuleOwner::Guild(_) => {
+                module.author_id() == user_id
+                    || permissions.is_some_and(|permissions| permissions.manage_guild())
+            }
+        }
+    }
+}
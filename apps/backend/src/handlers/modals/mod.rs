@@ -4,12 +4,14 @@ use crate::{AppState, error::Error, models::custom_id::CustomId};
 use anyhow::anyhow;
 use serenity::all::{CreateModal, ModalInteraction};
 
+mod ai_reply;
 mod code;
+mod math_module;
 
 pub trait ModalHandler {
     async fn handle_modal(interaction: ModalInteraction, state: Arc<AppState>)
     -> Result<(), Error>;
-    fn modal(data: Option<Vec<String>>) -> CreateModal;
+    fn modal(data: Option<Vec<String>>) -> Result<CreateModal, Error>;
 }
 
 pub async fn handle_interaction(
@@ -19,9 +21,13 @@ pub async fn handle_interaction(
     let custom_id = CustomId::try_from(interaction.data.custom_id.clone())?;
 
     match custom_id.id.as_ref() {
+        "ai_reply" => AiReplyModal::handle_modal(interaction.clone(), state.clone()).await,
         "code" => CodeModal::handle_modal(interaction.clone(), state.clone()).await,
+        "math_module" => MathModuleModal::handle_modal(interaction.clone(), state.clone()).await,
         name => Err(anyhow!("Modal with ID '{}' not found", name)),
     }
 }
 
+pub use ai_reply::AiReplyModal;
 pub use code::CodeModal;
+pub use math_module::{MAX_MODULES, MathModuleModal, ModuleError};
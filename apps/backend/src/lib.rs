@@ -0,0 +1,4 @@
+//! Parts of the bot that do not depend on its state, shared with the benchmarks.
+
+pub mod error;
+pub mod math;
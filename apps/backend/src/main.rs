@@ -1,20 +1,25 @@
 use std::net::SocketAddr;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use axum::Router;
 use axum::routing::{get, post};
 use clap::Parser;
 use env::ENV;
 use error::Error;
-use handlers::commands::CommandHandler;
+use liege_bot::{error, math};
+use math::{EvaluationLimits, MathSessions, UnitCatalogue};
 use middleware::ratelimit::JwtKeyExtractor;
+use models::database::{Database, snippets::SnippetRepository};
 use reqwest::Client;
-use serenity::all::{
-    ApplicationId, CommandType, CreateCommand, EntryPointHandlerType, GuildId, InstallationContext,
-    InteractionContext,
-};
+use serenity::all::ApplicationId;
 use serenity::interactions_endpoint::Verifier;
+use services::exchange_rates::ExchangeRateProvider;
+use services::{
+    ai::AiClient,
+    code::{LanguageCatalogue, PistonExecutor},
+};
 use tower_governor::GovernorLayer;
 use tower_governor::governor::GovernorConfigBuilder;
 use tower_http::cors::CorsLayer;
@@ -23,36 +28,79 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 mod args;
 mod controllers;
 mod env;
-mod error;
+mod games;
 mod handlers;
-mod math;
 mod middleware;
 mod models;
+mod registration;
+mod services;
+
+const LANGUAGE_REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Every math session keeps a worker process, so this also bounds the number of processes.
+const MATH_SESSION_CAPACITY: usize = 50;
+const MATH_SESSION_TTL: Duration = Duration::from_secs(30 * 60);
+const MATH_SESSION_EXPIRY_INTERVAL: Duration = Duration::from_secs(60);
+const SNIPPET_RETENTION: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+const SNIPPET_PURGE_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
 
 pub struct AppState {
     verifier: Verifier,
     http_client: reqwest::Client,
     serenity_http: serenity::http::Http,
+    database: Database,
+    code_executor: PistonExecutor,
+    ai: AiClient,
+    languages: LanguageCatalogue,
+    math_sessions: MathSessions,
+    units: UnitCatalogue,
 }
 
-impl Default for AppState {
-    fn default() -> Self {
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let http_client = Client::default();
+
         let state = Self {
             verifier: Verifier::new(&ENV.discord_public_key),
-            http_client: Client::default(),
+            code_executor: PistonExecutor::new(
+                http_client.clone(),
+                &ENV.code_api_url,
+                &ENV.code_token,
+            ),
+            ai: AiClient::new(
+                http_client.clone(),
+                &ENV.ai_api_url,
+                &ENV.ai_token,
+                &ENV.ai_text_model,
+            ),
+            languages: LanguageCatalogue::new(),
+            math_sessions: MathSessions::new(
+                MATH_SESSION_CAPACITY,
+                MATH_SESSION_TTL,
+                EvaluationLimits::default(),
+                std::env::current_exe()?,
+            ),
+            units: UnitCatalogue::new(),
+            http_client,
             serenity_http: serenity::http::Http::new(&ENV.discord_token),
+            database: Database::connect(&ENV.database_url).await?,
         };
 
         state.serenity_http.set_application_id(
             ApplicationId::from_str(&ENV.discord_app_id).expect("Invalid Application ID"),
         );
 
-        state
+        Ok(state)
     }
 }
 
 async fn run() -> Result<(), Error> {
-    let state = Arc::new(AppState::default());
+    let state = Arc::new(AppState::new().await?);
+    state.database.migrate().await?;
+
+    load_exchange_rates(&state).await;
+    tokio::spawn(refresh_languages(state.clone()));
+    tokio::spawn(expire_math_sessions(state.clone()));
+    tokio::spawn(purge_snippets(state.clone()));
 
     let api_governor_config = Arc::new(
         GovernorConfigBuilder::default()
@@ -74,6 +122,7 @@ async fn run() -> Result<(), Error> {
     let api_router = Router::new()
         .route("/ai", post(controllers::ai::post))
         .route("/code", post(controllers::code::post))
+        .route("/code/languages", get(controllers::code::languages))
         .route("/math", post(controllers::math::post))
         .layer(GovernorLayer {
             config: api_governor_config,
@@ -108,62 +157,81 @@ async fn run() -> Result<(), Error> {
     Ok(())
 }
 
-async fn register_commands(guild_id: Option<String>) -> Result<(), Error> {
-    let http = serenity::http::Http::new(&ENV.discord_token);
-
-    http.set_application_id(ApplicationId::from_str(&ENV.discord_app_id)?);
+/// Keeps the language catalogue up to date with the runtimes of the execution service.
+async fn refresh_languages(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(LANGUAGE_REFRESH_INTERVAL);
 
-    {
-        let global_commands = http.get_global_commands().await?;
-        let entry_point_id = global_commands
-            .iter()
-            .find(|c| c.kind == CommandType::PrimaryEntryPoint)
-            .map(|c| c.id);
+    loop {
+        interval.tick().await;
 
-        if let Some(entry_point_id) = entry_point_id {
-            serenity::all::Command::delete_global_command(&http, entry_point_id).await?;
+        if let Err(error) = state.languages.refresh(&state.code_executor).await {
+            tracing::error!(%error, "failed to refresh language catalogue");
         }
     }
+}
 
-    let entry_point_command = CreateCommand::new("launch")
-        .kind(CommandType::PrimaryEntryPoint)
-        .description("Launch the Liege activity")
-        .handler(EntryPointHandlerType::DiscordLaunchActivity)
-        .integration_types(vec![InstallationContext::Guild, InstallationContext::User])
-        .contexts(vec![
-            InteractionContext::Guild,
-            InteractionContext::BotDm,
-            InteractionContext::PrivateChannel,
-        ]);
-
-    let commands = vec![
-        entry_point_command,
-        handlers::commands::MathCommand::command(),
-        handlers::commands::CodeCommand::command(),
-        handlers::commands::AiCommand::command(),
-    ];
-
-    match guild_id {
-        Some(guild_id) => {
-            let guild_id = GuildId::from_str(&guild_id)?;
-            println!(
-                "Registering {} commands for guild {}",
-                commands.len(),
-                guild_id
-            );
-            guild_id.set_commands(&http, commands).await?;
-        }
-        None => {
-            println!("Registering {} global commands", commands.len());
-            serenity::all::Command::set_global_commands(&http, commands).await?;
+/// Drops idle math sessions, so their worker processes do not wait for the next evaluation to
+/// be killed.
+async fn expire_math_sessions(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(MATH_SESSION_EXPIRY_INTERVAL);
+
+    loop {
+        interval.tick().await;
+        state.math_sessions.expire();
+    }
+}
+
+/// Deletes code snippets older than [`SNIPPET_RETENTION`], which disables the buttons on their
+/// results.
+async fn purge_snippets(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(SNIPPET_PURGE_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let cutoff = chrono::Utc::now() - SNIPPET_RETENTION;
+
+        match state.database.delete_snippets_before(cutoff).await {
+            Ok(deleted) => tracing::debug!(deleted, "purged old code snippets"),
+            Err(error) => tracing::error!(%error, "failed to purge old code snippets"),
         }
     }
+}
+
+/// Loads the exchange rates for currency units in math evaluations. numbat only accepts rates
+/// once, so they stay the same until the next restart.
+async fn load_exchange_rates(state: &AppState) {
+    let rates = ExchangeRateProvider::new(
+        state.http_client.clone(),
+        &ENV.exchange_rates_url,
+        &ENV.exchange_rates_cache,
+    )
+    .load()
+    .await;
+
+    tracing::info!(origin = ?rates.origin, "loaded exchange rates");
+
+    math::install_exchange_rates(&rates.xml);
+}
+
+async fn migrate() -> Result<(), Error> {
+    let database = Database::connect(&ENV.database_url).await?;
+    database.migrate().await?;
+
+    println!("Applied all migrations to {}", ENV.database_url);
 
     Ok(())
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
+    let args = args::Cli::parse();
+
+    // Workers answer the webserver on stdout, so they start before anything can log there
+    if let args::Command::MathWorker { max_memory } = args.command() {
+        return Ok(math::run_worker(max_memory)?);
+    }
+
     dotenv::dotenv().ok();
 
     tracing_subscriber::registry()
@@ -181,10 +249,18 @@ async fn main() -> Result<(), Error> {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    let args = args::Cli::parse();
-
     match args.command() {
         args::Command::Run => run().await,
-        args::Command::RegisterCommands { guild_id } => register_commands(guild_id).await,
+        args::Command::RegisterCommands { guild_id, dry_run } => {
+            registration::register(registration::Scope::new(guild_id)?, dry_run).await
+        }
+        args::Command::Migrate => migrate().await,
+        args::Command::ListCommands { guild_id } => {
+            registration::list(registration::Scope::new(guild_id)?).await
+        }
+        args::Command::DeleteCommand { name, guild_id } => {
+            registration::delete(registration::Scope::new(guild_id)?, &name).await
+        }
+        args::Command::MathWorker { .. } => unreachable!("workers are started above"),
     }
 }
@@ -1,28 +1,30 @@
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, LazyLock, Mutex, OnceLock};
 
 use codespan_reporting::term::termcolor::WriteColor;
-use numbat::{NumbatError, buffered_writer::BufferedWriter, markup::Formatter};
-
-macro_rules! push_format {
-  ($string:ident, $($arg:tt)*) => {{
-      let formatted = format!($($arg)*);
-      $string.push_str(&formatted);
-  }};
-}
-
-macro_rules! push_formatln {
-  ($string:ident, $($arg:tt)*) => {{
-      push_format!($string, $($arg)*);
-      $string.push_str("\n");
-  }};
-}
-
-pub struct BufferWriter {
+use numbat::{
+    NumbatError,
+    buffered_writer::BufferedWriter,
+    markup::{Formatter, Markup},
+};
+
+mod modules;
+pub mod plot;
+pub mod render;
+mod sessions;
+mod units;
+mod worker;
+
+pub use modules::{MAX_CODE_LENGTH, MAX_NAME_LENGTH, Modules, validate_name};
+pub use sessions::{EvaluationError, EvaluationLimits, MathSessions};
+pub use units::{Conversion, UnitCatalogue};
+pub use worker::{WORKER_COMMAND, run_worker};
+
+struct BufferWriter {
     buffer: Vec<u8>,
 }
 
 impl BufferWriter {
-    pub fn new() -> Self {
+    fn new() -> Self {
         BufferWriter { buffer: vec![] }
     }
 }
@@ -60,121 +62,123 @@ impl WriteColor for BufferWriter {
     }
 }
 
-trait EvaluateToString {
-    fn evaluate_to_string(&mut self, input: &str, html: bool) -> Result<String, String>;
-}
+trait Evaluate {
+    /// Evaluates `input`, returning the output of print statements followed by the result.
+    /// `html` only applies to the diagnostics returned on errors.
+    fn evaluate_to_markup(&mut self, input: &str, html: bool) -> Result<Markup, String>;
 
-impl EvaluateToString for numbat::Context {
     fn evaluate_to_string(&mut self, input: &str, html: bool) -> Result<String, String> {
-        let mut output = String::new();
-
-        let mut push_diagnostics =
-            |this: &Self, error: Box<dyn numbat::diagnostic::ErrorDiagnostic>| {
-                let config = codespan_reporting::term::Config::default();
-                let mut buffer: Box<dyn BufferedWriter> = if html {
-                    Box::new(numbat::html_formatter::HtmlWriter::new())
-                } else {
-                    Box::new(BufferWriter::new())
-                };
-
-                for diagnostic in error.diagnostics() {
-                    codespan_reporting::term::emit(
-                        &mut buffer,
-                        &config,
-                        &this.resolver().files,
-                        &diagnostic,
-                    )
-                    .unwrap();
-                }
-
-                let formatted = buffer.to_string();
-                output.push_str(&formatted);
-            };
-
-        let to_be_printed: Arc<Mutex<Vec<numbat::markup::Markup>>> =
-            Arc::new(Mutex::new(Vec::new()));
+        let markup = self.evaluate_to_markup(input, html)?;
+
+        let output = if html {
+            numbat::html_formatter::HtmlFormatter
+                .format(&markup, true)
+                .to_string()
+        } else {
+            markup.to_string()
+        };
+
+        Ok(output.trim().to_owned())
+    }
+}
+
+impl Evaluate for numbat::Context {
+    fn evaluate_to_markup(&mut self, input: &str, html: bool) -> Result<Markup, String> {
+        let to_be_printed: Arc<Mutex<Vec<Markup>>> = Arc::new(Mutex::new(Vec::new()));
         let to_be_printed_cloned = to_be_printed.clone();
 
         let mut settings = numbat::InterpreterSettings {
-            print_fn: Box::new(move |s: &numbat::markup::Markup| {
+            print_fn: Box::new(move |s: &Markup| {
                 to_be_printed_cloned.lock().unwrap().push(s.clone());
             }),
         };
 
-        let result =
-            self.interpret_with_settings(&mut settings, input, numbat::resolver::CodeSource::Text);
-
-        let is_ok = result.is_ok();
-
-        match result.map_err(|b| *b) {
-            Ok((statements, interpreter_result)) => {
-                let to_be_printed = to_be_printed.lock().unwrap();
-                for s in to_be_printed.iter() {
-                    if html {
-                        let fmt = numbat::html_formatter::HtmlFormatter;
-                        let s = fmt.format(&s, true);
-
-                        push_formatln!(output, "{}", s);
-                    } else {
-                        push_formatln!(output, "{}", s);
-                    }
-                }
-
-                let registry = self.dimension_registry();
-                let result_markup =
-                    interpreter_result.to_markup(statements.last(), registry, true, html);
-
-                if html {
-                    let fmt = numbat::html_formatter::HtmlFormatter;
-                    let s = fmt.format(&result_markup, true);
-
-                    push_format!(output, "{}", s);
-                } else {
-                    push_format!(output, "{}", result_markup);
-                }
-            }
-
-            Err(NumbatError::ResolverError(e)) => {
-                push_diagnostics(self, Box::new(e));
-            }
-            Err(NumbatError::NameResolutionError(
-                e @ (numbat::NameResolutionError::IdentifierClash { .. }
-                | numbat::NameResolutionError::ReservedIdentifier(_)),
-            )) => {
-                push_diagnostics(self, Box::new(e));
-            }
-            Err(NumbatError::TypeCheckError(e)) => {
-                push_diagnostics(self, Box::new(e));
-            }
-            Err(NumbatError::RuntimeError(e)) => {
-                push_diagnostics(self, Box::new(e));
-            }
+        let (statements, interpreter_result) = self
+            .interpret_with_settings(&mut settings, input, numbat::resolver::CodeSource::Text)
+            .map_err(|error| diagnostics(self, *error, html))?;
+
+        let mut output = Markup::default();
+
+        for s in to_be_printed.lock().unwrap().iter() {
+            output += s.clone() + numbat::markup::nl();
         }
 
-        let output = output.trim().to_owned();
+        let registry = self.dimension_registry();
+        output += interpreter_result.to_markup(statements.last(), registry, true, html);
 
-        if is_ok { Ok(output) } else { Err(output) }
+        Ok(output)
     }
 }
 
-pub fn evaluate(input: &str) -> Result<String, String> {
-    let mut context =
-        numbat::Context::new(numbat::module_importer::BuiltinModuleImporter::default());
-
-    let _ = context
-        .interpret("use prelude", numbat::resolver::CodeSource::Internal)
+/// Formats the diagnostics of `error` like the numbat CLI does.
+fn diagnostics(context: &numbat::Context, error: NumbatError, html: bool) -> String {
+    let error: Box<dyn numbat::diagnostic::ErrorDiagnostic> = match error {
+        NumbatError::ResolverError(e) => Box::new(e),
+        NumbatError::NameResolutionError(e) => Box::new(e),
+        NumbatError::TypeCheckError(e) => Box::new(e),
+        NumbatError::RuntimeError(e) => Box::new(e),
+    };
+
+    let config = codespan_reporting::term::Config::default();
+    let mut buffer: Box<dyn BufferedWriter> = if html {
+        Box::new(numbat::html_formatter::HtmlWriter::new())
+    } else {
+        Box::new(BufferWriter::new())
+    };
+
+    for diagnostic in error.diagnostics() {
+        codespan_reporting::term::emit(
+            &mut buffer,
+            &config,
+            &context.resolver().files,
+            &diagnostic,
+        )
         .unwrap();
+    }
 
-    context.evaluate_to_string(input, false)
+    buffer.to_string().trim().to_owned()
 }
 
-pub fn evaluate_html(input: &str) -> Result<String, String> {
-    let mut context =
-        numbat::Context::new(numbat::module_importer::BuiltinModuleImporter::default());
+/// Context with the prelude loaded. Loading the prelude takes far longer than evaluating most
+/// expressions, so it is only done once and cloned for every session.
+static PRELUDE: LazyLock<numbat::Context> = LazyLock::new(|| {
+    let mut context = numbat::Context::new(modules::Importer::default());
 
     let _ = context
         .interpret("use prelude", numbat::resolver::CodeSource::Internal)
         .unwrap();
 
-    context.evaluate_to_string(input, true)
+    // Currency units need exchange rates, which are only loaded once a currency is used
+    context.load_currency_module_on_demand(true);
+
+    context
+});
+
+/// Exchange rates installed by [`install_exchange_rates`], which are sent to every worker.
+static EXCHANGE_RATES: OnceLock<String> = OnceLock::new();
+
+/// Makes currency units available using exchange rates in the XML format of the ECB.
+///
+/// numbat keeps the rates in a global that can only be set once, so this must be called before
+/// the first evaluation that uses a currency, and later calls are ignored and return `false`.
+pub fn install_exchange_rates(xml: &str) -> bool {
+    let mut installed = false;
+
+    EXCHANGE_RATES.get_or_init(|| {
+        numbat::Context::set_exchange_rates(xml);
+        installed = true;
+
+        xml.to_owned()
+    });
+
+    installed
+}
+
+fn exchange_rates() -> Option<&'static str> {
+    EXCHANGE_RATES.get().map(String::as_str)
+}
+
+/// Creates a context with the prelude loaded.
+fn prelude_context() -> numbat::Context {
+    PRELUDE.clone()
 }
@@ -0,0 +1,201 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    path::PathBuf,
+    sync::LazyLock,
+};
+
+use numbat::{
+    compact_str::CompactString,
+    module_importer::{BuiltinModuleImporter, ModuleImporter},
+    resolver::ModulePath,
+};
+use serde::{Deserialize, Serialize};
+
+/// Maximum length of module names, they are stored in custom ids which are limited to 100
+/// characters.
+pub const MAX_NAME_LENGTH: usize = 64;
+
+/// Maximum length of the code of modules, they are entered in modals whose inputs are limited
+/// to 4000 characters.
+pub const MAX_CODE_LENGTH: usize = 4000;
+
+static BUILTIN_MODULES: LazyLock<Vec<ModulePath>> =
+    LazyLock::new(|| BuiltinModuleImporter::default().list_modules());
+
+thread_local! {
+    /// Modules importable by the evaluation running on this thread. The importer is shared by
+    /// every context cloned from the prelude, so it can not hold the modules of one user.
+    static MODULES: RefCell<Modules> = RefCell::default();
+
+    /// Length of the code of the modules imported from [`MODULES`].
+    static IMPORTED: Cell<usize> = const { Cell::new(0) };
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Module {
+    /// Shown as the file of the module in diagnostics, e.g. `<guild>`.
+    origin: String,
+    code: String,
+}
+
+/// Modules that can be imported in addition to the builtin ones, by names like
+/// `team::constants`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Modules {
+    modules: HashMap<String, Module>,
+}
+
+impl Modules {
+    /// Adds the module `name` unless a module with the same name was added before, so modules
+    /// must be added from the highest to the lowest precedence.
+    pub fn add(&mut self, name: &str, origin: &str, code: &str) {
+        self.modules
+            .entry(name.to_string())
+            .or_insert_with(|| Module {
+                origin: origin.to_string(),
+                code: code.to_string(),
+            });
+    }
+
+    fn get(&self, path: &ModulePath) -> Option<&Module> {
+        self.modules.get(&path.to_string())
+    }
+}
+
+/// Runs `job` with `modules` importable by the contexts it evaluates in.
+pub(super) fn with_modules<T>(modules: Modules, job: impl FnOnce() -> T) -> T {
+    let previous = MODULES.replace(modules);
+    IMPORTED.set(0);
+
+    let result = job();
+    MODULES.set(previous);
+
+    result
+}
+
+/// Returns the length of the code of the modules imported so far by the job running in
+/// [`with_modules`].
+pub(super) fn imported_size() -> usize {
+    IMPORTED.get()
+}
+
+/// Imports the builtin modules, followed by the modules set with [`with_modules`].
+#[derive(Debug, Clone, Default)]
+pub(super) struct Importer {
+    builtin: BuiltinModuleImporter,
+}
+
+impl ModuleImporter for Importer {
+    fn import(&self, path: &ModulePath) -> Option<(String, Option<PathBuf>)> {
+        self.builtin.import(path).or_else(|| {
+            MODULES.with_borrow(|modules| {
+                let module = modules.get(path)?;
+                IMPORTED.set(IMPORTED.get() + module.code.len());
+
+                let file = PathBuf::from(&module.origin).join(path.0.join("/"));
+
+                Some((module.code.clone(), Some(file.with_extension("nbt"))))
+            })
+        })
+    }
+
+    fn list_modules(&self) -> Vec<ModulePath> {
+        let mut modules = self.builtin.list_modules();
+
+        MODULES.with_borrow(|saved| {
+            modules.extend(
+                saved
+                    .modules
+                    .keys()
+                    .map(|name| ModulePath(name.split("::").map(CompactString::from).collect())),
+            );
+        });
+
+        modules.sort();
+        modules.dedup();
+
+        modules
+    }
+}
+
+/// Checks that `name` can be used as the name of a module, e.g. `team::constants`.
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.len() > MAX_NAME_LENGTH {
+        return Err(format!(
+            "Module names can be at most {MAX_NAME_LENGTH} characters long"
+        ));
+    }
+
+    let valid_segment = |segment: &str| {
+        segment
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && segment
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+
+    if !name.split("::").all(valid_segment) {
+        return Err(String::from(
+            "Module names must consist of letters, digits and underscores separated by `::`, e.g. `team::constants`",
+        ));
+    }
+
+    // Builtin modules are imported first, a module with the same name could never be used
+    if BUILTIN_MODULES.iter().any(|path| path.to_string() == name) {
+        return Err(format!("`{name}` is the name of a builtin module"));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{Evaluate, prelude_context};
+
+    #[test]
+    fn imports_modules() {
+        let mut modules = Modules::default();
+        modules.add("team::constants", "<user>", "let answer = 42");
+        modules.add("team::constants", "<guild>", "let answer = 0");
+
+        let result = with_modules(modules, || {
+            prelude_context().evaluate_to_string("use team::constants\nanswer", false)
+        });
+        assert_eq!(result.unwrap(), "42");
+
+        // Modules are only importable inside `with_modules`
+        let error = prelude_context()
+            .evaluate_to_string("use team::constants", false)
+            .unwrap_err();
+        assert!(error.contains("Unknown module"), "{error}");
+    }
+
+    #[test]
+    fn prefers_builtin_modules() {
+        let mut modules = Modules::default();
+        modules.add("core::strings", "<user>", "let x = 1");
+
+        let result = with_modules(modules, || {
+            prelude_context().evaluate_to_string("use core::strings\nx", false)
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn validates_names() {
+        assert!(validate_name("team::constants").is_ok());
+        assert!(validate_name("_private2").is_ok());
+
+        assert!(validate_name("").is_err());
+        assert!(validate_name("team::").is_err());
+        assert!(validate_name("team:constants").is_err());
+        assert!(validate_name("2team").is_err());
+        assert!(validate_name("team,constants").is_err());
+        assert!(validate_name(&"a".repeat(MAX_NAME_LENGTH + 1)).is_err());
+        assert!(validate_name("units::si").is_err());
+    }
+}
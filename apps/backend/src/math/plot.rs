@@ -0,0 +1,138 @@
+use numbat::{
+    InterpreterResult, InterpreterSettings, markup::Markup, resolver::CodeSource, value::Value,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::diagnostics;
+
+/// Number of points a function is sampled at.
+pub const SAMPLES: usize = 200;
+
+/// Values of a function of `x`, with `y` converted to a common unit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Plot {
+    pub expression: String,
+    /// Unit of the `y` values, empty for scalars.
+    pub unit: String,
+    /// Points in order of `x`, `y` is not finite where the function is undefined.
+    #[serde(deserialize_with = "deserialize_points")]
+    pub points: Vec<(f64, f64)>,
+}
+
+/// JSON has no numbers that are not finite, they are serialized as `null` and read back as NaN.
+fn deserialize_points<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<(f64, f64)>, D::Error> {
+    let points: Vec<(f64, Option<f64>)> = Vec::deserialize(deserializer)?;
+
+    Ok(points
+        .into_iter()
+        .map(|(x, y)| (x, y.unwrap_or(f64::NAN)))
+        .collect())
+}
+
+/// Samples `expression` at [`SAMPLES`] evenly spaced values of `x` from `from` to `to`.
+pub fn sample(
+    context: &mut numbat::Context,
+    expression: &str,
+    from: f64,
+    to: f64,
+) -> Result<Plot, String> {
+    if !from.is_finite() || !to.is_finite() || from >= to {
+        return Err(String::from(
+            "The start of the range must be less than the end",
+        ));
+    }
+
+    let xs: Vec<f64> = (0..SAMPLES)
+        .map(|i| from + (to - from) * i as f64 / (SAMPLES - 1) as f64)
+        .collect();
+
+    let list = xs
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    // The whole range is evaluated at once, interpreting every sample on its own is a lot slower
+    let code = format!("fn _plot(x) = {expression}\nmap(_plot, [{list}])");
+
+    let mut settings = InterpreterSettings {
+        print_fn: Box::new(|_: &Markup| {}),
+    };
+
+    let result = context
+        .interpret_with_settings(&mut settings, &code, CodeSource::Text)
+        .map_err(|error| diagnostics(context, *error, false))?;
+
+    let (_, InterpreterResult::Value(Value::List(values))) = result else {
+        return Err(String::from("The expression must be a function of `x`"));
+    };
+
+    let mut unit = None;
+    let mut points = Vec::with_capacity(SAMPLES);
+
+    for (x, value) in xs.into_iter().zip(values.iter()) {
+        let Value::Quantity(quantity) = value else {
+            return Err(String::from("The expression must evaluate to a number"));
+        };
+
+        let unit = unit.get_or_insert_with(|| quantity.unit().clone());
+
+        let y = quantity
+            .convert_to(unit)
+            .map(|quantity| quantity.unsafe_value().to_f64())
+            .unwrap_or(f64::NAN);
+
+        points.push((x, y));
+    }
+
+    if !points.iter().any(|(_, y)| y.is_finite()) {
+        return Err(String::from(
+            "The expression is not defined anywhere in the range",
+        ));
+    }
+
+    Ok(Plot {
+        expression: expression.to_string(),
+        unit: unit.map(|unit| unit.to_string()).unwrap_or_default(),
+        points,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{Evaluate, prelude_context};
+
+    #[test]
+    fn samples_functions() {
+        let plot = sample(&mut prelude_context(), "x^2", -1.0, 1.0).unwrap();
+
+        assert_eq!(plot.points.len(), SAMPLES);
+        assert_eq!(plot.points[0], (-1.0, 1.0));
+        assert_eq!(plot.points[SAMPLES - 1], (1.0, 1.0));
+        assert!(plot.unit.is_empty());
+    }
+
+    #[test]
+    fn keeps_units() {
+        let mut context = prelude_context();
+        context.evaluate_to_string("let x = 3", false).unwrap();
+        context
+            .evaluate_to_string("fn speed(t) = 2 m/s * t", false)
+            .unwrap();
+
+        let plot = sample(&mut context, "speed(x)", 0.0, 10.0).unwrap();
+
+        assert_eq!(plot.unit, "m/s");
+        assert_eq!(plot.points[SAMPLES - 1], (10.0, 20.0));
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        assert!(sample(&mut prelude_context(), "x", 1.0, 1.0).is_err());
+        assert!(sample(&mut prelude_context(), "x +", 0.0, 1.0).is_err());
+        assert!(sample(&mut prelude_context(), "\"text\"", 0.0, 1.0).is_err());
+    }
+}
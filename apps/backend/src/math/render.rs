@@ -0,0 +1,561 @@
+use std::sync::LazyLock;
+
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont, point};
+use anyhow::bail;
+use numbat::markup::{FormatType, Markup};
+use tiny_skia::{Color, Paint, PathBuilder, Pixmap, PremultipliedColorU8, Rect, Stroke, Transform};
+
+use crate::error::Error;
+
+use super::plot::Plot;
+
+const FONT_SIZE: f32 = 24.0;
+const PADDING: f32 = 20.0;
+
+/// Lines longer than this are wrapped.
+const MAX_COLUMNS: usize = 80;
+
+/// Lines after this are cut off, so long outputs do not turn into huge images.
+const MAX_LINES: usize = 40;
+
+const PLOT_WIDTH: u32 = 800;
+const PLOT_HEIGHT: u32 = 480;
+const PLOT_FONT_SIZE: f32 = 16.0;
+const PLOT_TITLE_SIZE: f32 = 20.0;
+
+/// Space around the plot area for the title and the tick labels.
+const PLOT_MARGIN: Margin = Margin {
+    top: 56.0,
+    right: 28.0,
+    bottom: 40.0,
+    left: 88.0,
+};
+
+/// Approximate number of ticks on each axis.
+const TICK_COUNT: f64 = 6.0;
+
+/// Most ticks on each axis, rounding can make the step far smaller than intended for ranges
+/// close to the precision of `f64`.
+const MAX_TICKS: usize = 4 * TICK_COUNT as usize;
+
+struct Margin {
+    top: f32,
+    right: f32,
+    bottom: f32,
+    left: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rgb(u8, u8, u8);
+
+// Colors of the syntax highlighting in the frontend, see `numbat-syntax.css`
+const BACKGROUND: Rgb = Rgb(0x1e, 0x1f, 0x22);
+const TEXT: Rgb = Rgb(0xdb, 0xde, 0xe1);
+const DIMMED: Rgb = Rgb(0x88, 0x88, 0x88);
+const STRING: Rgb = Rgb(0x59, 0xf7, 0x8d);
+const KEYWORD: Rgb = Rgb(0xff, 0x69, 0xc0);
+const VALUE: Rgb = Rgb(0xf3, 0xf9, 0x9d);
+const UNIT: Rgb = Rgb(0x99, 0xec, 0xfe);
+const TYPE_IDENTIFIER: Rgb = Rgb(0x57, 0xc7, 0xff);
+const GRID: Rgb = Rgb(0x2e, 0x30, 0x35);
+const AXIS: Rgb = Rgb(0x4e, 0x50, 0x58);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Style {
+    color: Rgb,
+    bold: bool,
+}
+
+impl Style {
+    const fn new(color: Rgb) -> Self {
+        Self { color, bold: false }
+    }
+
+    const fn bold(color: Rgb) -> Self {
+        Self { color, bold: true }
+    }
+
+    fn of(format: FormatType) -> Self {
+        match format {
+            FormatType::Whitespace | FormatType::Text | FormatType::Identifier => Self::new(TEXT),
+            FormatType::Emphasized | FormatType::Operator => Self::bold(TEXT),
+            FormatType::Dimmed => Self::new(DIMMED),
+            FormatType::String | FormatType::Decorator => Self::new(STRING),
+            FormatType::Keyword => Self::bold(KEYWORD),
+            FormatType::Value => Self::new(VALUE),
+            FormatType::Unit => Self::new(UNIT),
+            FormatType::TypeIdentifier => Self::new(TYPE_IDENTIFIER),
+        }
+    }
+}
+
+struct Fonts {
+    regular: FontRef<'static>,
+    bold: FontRef<'static>,
+}
+
+static FONTS: LazyLock<Fonts> = LazyLock::new(|| Fonts {
+    regular: FontRef::try_from_slice(include_bytes!("../../assets/fonts/DejaVuSansMono.ttf"))
+        .unwrap(),
+    bold: FontRef::try_from_slice(include_bytes!("../../assets/fonts/DejaVuSansMono-Bold.ttf"))
+        .unwrap(),
+});
+
+impl Fonts {
+    fn get(&self, style: Style) -> &FontRef<'static> {
+        if style.bold {
+            &self.bold
+        } else {
+            &self.regular
+        }
+    }
+
+    /// Width of a character, the fonts are monospaced.
+    fn advance(&self, size: f32) -> f32 {
+        let font = self.regular.as_scaled(PxScale::from(size));
+        font.h_advance(font.glyph_id('0'))
+    }
+
+    fn line_height(&self, size: f32) -> f32 {
+        self.regular.as_scaled(PxScale::from(size)).height()
+    }
+}
+
+/// Renders markup with syntax highlighting to a PNG image.
+pub fn render_markup(markup: &Markup) -> Result<Vec<u8>, Error> {
+    let lines = layout(markup);
+
+    let advance = FONTS.advance(FONT_SIZE);
+    let line_height = FONTS.line_height(FONT_SIZE);
+    let columns = lines.iter().map(Vec::len).max().unwrap_or(0).max(1);
+
+    let width = (columns as f32 * advance + 2.0 * PADDING).ceil() as u32;
+    let height = (lines.len() as f32 * line_height + 2.0 * PADDING).ceil() as u32;
+
+    let mut pixmap = canvas(width, height)?;
+
+    for (row, line) in lines.iter().enumerate() {
+        let y = PADDING + row as f32 * line_height;
+
+        for (column, (character, style)) in line.iter().enumerate() {
+            let x = PADDING + column as f32 * advance;
+            draw_char(&mut pixmap, *character, x, y, FONT_SIZE, *style);
+        }
+    }
+
+    Ok(pixmap.encode_png()?)
+}
+
+/// Renders a line chart of `plot` to a PNG image.
+pub fn render_plot(plot: &Plot) -> Result<Vec<u8>, Error> {
+    let mut pixmap = canvas(PLOT_WIDTH, PLOT_HEIGHT)?;
+
+    let area = Rect::from_ltrb(
+        PLOT_MARGIN.left,
+        PLOT_MARGIN.top,
+        PLOT_WIDTH as f32 - PLOT_MARGIN.right,
+        PLOT_HEIGHT as f32 - PLOT_MARGIN.bottom,
+    )
+    .unwrap();
+
+    let (x_min, x_max) = bounds(plot.points.iter().map(|(x, _)| *x));
+    let (y_min, y_max) = bounds(plot.points.iter().map(|(_, y)| *y));
+
+    // Keep the curve off the top and bottom edges
+    let padding = (y_max - y_min) * 0.05;
+    let (y_min, y_max) = (y_min - padding, y_max + padding);
+
+    // Ranges beyond the largest `f64`, e.g. of `x * 1e308`, can not be scaled to the image
+    if !(x_max - x_min).is_finite() || !(y_max - y_min).is_finite() {
+        bail!("The values are too large to plot");
+    }
+
+    let to_pixel = |x: f64, y: f64| {
+        (
+            area.left() + ((x - x_min) / (x_max - x_min)) as f32 * area.width(),
+            area.bottom() - ((y - y_min) / (y_max - y_min)) as f32 * area.height(),
+        )
+    };
+
+    let label_advance = FONTS.advance(PLOT_FONT_SIZE);
+    let label_height = FONTS.line_height(PLOT_FONT_SIZE);
+
+    for tick in ticks(x_min, x_max) {
+        let (x, _) = to_pixel(tick.value, y_min);
+        line(&mut pixmap, (x, area.top()), (x, area.bottom()), GRID, 1.0);
+
+        let width = tick.label.chars().count() as f32 * label_advance;
+        let style = Style::new(DIMMED);
+        draw_text(
+            &mut pixmap,
+            &tick.label,
+            x - width / 2.0,
+            area.bottom() + 8.0,
+            PLOT_FONT_SIZE,
+            style,
+        );
+    }
+
+    for tick in ticks(y_min, y_max) {
+        let (_, y) = to_pixel(x_min, tick.value);
+        line(&mut pixmap, (area.left(), y), (area.right(), y), GRID, 1.0);
+
+        let width = tick.label.chars().count() as f32 * label_advance;
+        let style = Style::new(DIMMED);
+        draw_text(
+            &mut pixmap,
+            &tick.label,
+            area.left() - width - 10.0,
+            y - label_height / 2.0,
+            PLOT_FONT_SIZE,
+            style,
+        );
+    }
+
+    // Axes through the origin, if it is in range
+    if x_min <= 0.0 && 0.0 <= x_max {
+        let (x, _) = to_pixel(0.0, y_min);
+        line(&mut pixmap, (x, area.top()), (x, area.bottom()), AXIS, 1.5);
+    }
+
+    if y_min <= 0.0 && 0.0 <= y_max {
+        let (_, y) = to_pixel(x_min, 0.0);
+        line(&mut pixmap, (area.left(), y), (area.right(), y), AXIS, 1.5);
+    }
+
+    let mut path = PathBuilder::new();
+    let mut drawing = false;
+
+    for (x, y) in &plot.points {
+        if !y.is_finite() {
+            drawing = false;
+            continue;
+        }
+
+        let (x, y) = to_pixel(*x, *y);
+
+        if drawing {
+            path.line_to(x, y);
+        } else {
+            path.move_to(x, y);
+            drawing = true;
+        }
+    }
+
+    if let Some(path) = path.finish() {
+        let stroke = Stroke {
+            width: 2.5,
+            ..Default::default()
+        };
+
+        pixmap.stroke_path(
+            &path,
+            &paint(TYPE_IDENTIFIER),
+            &stroke,
+            Transform::identity(),
+            None,
+        );
+    }
+
+    let mut title: String = plot.expression.chars().take(MAX_COLUMNS).collect();
+
+    if !plot.unit.is_empty() {
+        title.push_str(&format!(" [{}]", plot.unit));
+    }
+
+    draw_text(
+        &mut pixmap,
+        &title,
+        PLOT_MARGIN.left,
+        16.0,
+        PLOT_TITLE_SIZE,
+        Style::new(TEXT),
+    );
+
+    Ok(pixmap.encode_png()?)
+}
+
+/// Splits markup into lines of styled characters, wrapping long lines and cutting off the
+/// output after [`MAX_LINES`].
+fn layout(markup: &Markup) -> Vec<Vec<(char, Style)>> {
+    let mut lines = vec![vec![]];
+
+    for part in &markup.0 {
+        let style = Style::of(part.1);
+
+        for character in part.2.chars() {
+            let current = lines.last_mut().unwrap();
+
+            if character == '\n' {
+                lines.push(vec![]);
+            } else if current.len() >= MAX_COLUMNS {
+                lines.push(vec![(character, style)]);
+            } else {
+                current.push((character, style));
+            }
+        }
+    }
+
+    // Trailing empty lines would only add padding
+    while lines.len() > 1 && lines.last().is_some_and(Vec::is_empty) {
+        lines.pop();
+    }
+
+    if lines.len() > MAX_LINES {
+        lines.truncate(MAX_LINES);
+        lines.push("...".chars().map(|c| (c, Style::new(DIMMED))).collect());
+    }
+
+    lines
+}
+
+fn canvas(width: u32, height: u32) -> Result<Pixmap, Error> {
+    let mut pixmap = Pixmap::new(width, height)
+        .ok_or_else(|| anyhow::anyhow!("Invalid image size {width}x{height}"))?;
+
+    let Rgb(r, g, b) = BACKGROUND;
+    pixmap.fill(Color::from_rgba8(r, g, b, 255));
+
+    Ok(pixmap)
+}
+
+fn paint(color: Rgb) -> Paint<'static> {
+    let Rgb(r, g, b) = color;
+
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(r, g, b, 255);
+    paint
+}
+
+fn line(pixmap: &mut Pixmap, from: (f32, f32), to: (f32, f32), color: Rgb, width: f32) {
+    let mut path = PathBuilder::new();
+    path.move_to(from.0, from.1);
+    path.line_to(to.0, to.1);
+
+    let Some(path) = path.finish() else {
+        return;
+    };
+
+    let stroke = Stroke {
+        width,
+        ..Default::default()
+    };
+
+    pixmap.stroke_path(&path, &paint(color), &stroke, Transform::identity(), None);
+}
+
+/// Draws `text` with its top left corner at `x` and `y`.
+fn draw_text(pixmap: &mut Pixmap, text: &str, x: f32, y: f32, size: f32, style: Style) {
+    let advance = FONTS.advance(size);
+
+    for (column, character) in text.chars().enumerate() {
+        draw_char(
+            pixmap,
+            character,
+            x + column as f32 * advance,
+            y,
+            size,
+            style,
+        );
+    }
+}
+
+fn draw_char(pixmap: &mut Pixmap, character: char, x: f32, y: f32, size: f32, style: Style) {
+    let font = FONTS.get(style);
+    let scale = PxScale::from(size);
+    let baseline = y + font.as_scaled(scale).ascent();
+
+    let glyph = font
+        .glyph_id(character)
+        .with_scale_and_position(scale, point(x, baseline));
+
+    let Some(outline) = font.outline_glyph(glyph) else {
+        return;
+    };
+
+    let bounds = outline.px_bounds();
+    let width = pixmap.width() as i64;
+    let height = pixmap.height() as i64;
+    let pixels = pixmap.pixels_mut();
+    let Rgb(r, g, b) = style.color;
+
+    outline.draw(|gx, gy, coverage| {
+        let px = bounds.min.x as i64 + gx as i64;
+        let py = bounds.min.y as i64 + gy as i64;
+
+        if px < 0 || py < 0 || px >= width || py >= height {
+            return;
+        }
+
+        // The canvas is opaque, so premultiplied and straight colors are the same
+        let pixel = &mut pixels[(py * width + px) as usize];
+        let blend = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * coverage) as u8;
+
+        *pixel = PremultipliedColorU8::from_rgba(
+            blend(pixel.red(), r),
+            blend(pixel.green(), g),
+            blend(pixel.blue(), b),
+            255,
+        )
+        .unwrap();
+    });
+}
+
+/// Minimum and maximum of the finite values, widened if they are equal.
+fn bounds(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    let (min, max) = values
+        .filter(|value| value.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| {
+            (min.min(value), max.max(value))
+        });
+
+    if !min.is_finite() {
+        return (-1.0, 1.0);
+    }
+
+    if min == max {
+        let padding = if min == 0.0 { 1.0 } else { min.abs() / 10.0 };
+        return (min - padding, max + padding);
+    }
+
+    (min, max)
+}
+
+#[derive(Debug, PartialEq)]
+struct Tick {
+    value: f64,
+    label: String,
+}
+
+/// Evenly spaced ticks at round values from `min` to `max`.
+fn ticks(min: f64, max: f64) -> Vec<Tick> {
+    let raw_step = (max - min) / TICK_COUNT;
+    let magnitude = 10f64.powf(raw_step.log10().floor());
+
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|factor| factor * magnitude)
+        .find(|step| *step >= raw_step)
+        .unwrap_or(10.0 * magnitude);
+
+    if !step.is_normal() {
+        return vec![];
+    }
+
+    let use_exponent = !(1e-4..1e6).contains(&step);
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+
+    let mut ticks = vec![];
+    let first = (min / step).ceil();
+
+    for index in 0..MAX_TICKS {
+        let value = (first + index as f64) * step;
+
+        if value > max + step * 1e-9 {
+            break;
+        }
+
+        // Avoid labels like `-0`
+        let rounded = if value.abs() < step * 1e-9 {
+            0.0
+        } else {
+            value
+        };
+
+        let label = if use_exponent {
+            format!("{rounded:e}")
+        } else {
+            format!("{rounded:.decimals$}")
+        };
+
+        ticks.push(Tick {
+            value: rounded,
+            label,
+        });
+    }
+
+    ticks
+}
+
+#[cfg(test)]
+mod tests {
+    use numbat::{compact_str::CompactString, markup};
+
+    use super::*;
+
+    const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+    #[test]
+    fn lays_out_markup() {
+        let markup = markup::value("42")
+            + markup::space()
+            + markup::unit("m")
+            + markup::nl()
+            + markup::text(CompactString::from("a".repeat(MAX_COLUMNS + 1)));
+
+        let lines = layout(&markup);
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0][0], ('4', Style::new(VALUE)));
+        assert_eq!(lines[0][3], ('m', Style::new(UNIT)));
+        assert_eq!(lines[1].len(), MAX_COLUMNS);
+        assert_eq!(lines[2].len(), 1);
+    }
+
+    #[test]
+    fn cuts_off_long_output() {
+        let markup = markup::text(CompactString::from("line\n".repeat(MAX_LINES * 2)));
+        let lines = layout(&markup);
+
+        assert_eq!(lines.len(), MAX_LINES + 1);
+        assert_eq!(lines[MAX_LINES][0].0, '.');
+    }
+
+    #[test]
+    fn renders_png() {
+        let markup = markup::keyword("let") + markup::space() + markup::identifier("x");
+        let image = render_markup(&markup).unwrap();
+
+        assert!(image.starts_with(PNG_SIGNATURE));
+
+        let plot = Plot {
+            expression: String::from("1 / x"),
+            unit: String::new(),
+            points: vec![(-1.0, -1.0), (0.0, f64::INFINITY), (1.0, 1.0)],
+        };
+        let image = render_plot(&plot).unwrap();
+
+        assert!(image.starts_with(PNG_SIGNATURE));
+    }
+
+    #[test]
+    fn picks_round_ticks() {
+        let values = |min, max| -> Vec<f64> { ticks(min, max).iter().map(|t| t.value).collect() };
+
+        assert_eq!(values(0.0, 10.0), [0.0, 2.0, 4.0, 6.0, 8.0, 10.0]);
+        assert_eq!(values(-1.0, 1.0), [-1.0, -0.5, 0.0, 0.5, 1.0]);
+
+        let labels: Vec<_> = ticks(0.0, 0.5).into_iter().map(|t| t.label).collect();
+        assert_eq!(labels, ["0.0", "0.1", "0.2", "0.3", "0.4", "0.5"]);
+
+        assert_eq!(ticks(0.0, 1e7)[1].label, "2e6");
+    }
+
+    #[test]
+    fn limits_ticks() {
+        assert!(ticks(0.0, 5e-324).len() <= MAX_TICKS);
+        assert!(ticks(f64::MIN, f64::MAX).is_empty());
+        assert!(ticks(0.0, f64::NAN).is_empty());
+    }
+
+    #[test]
+    fn rejects_huge_ranges() {
+        let plot = Plot {
+            expression: String::from("x * 1e308"),
+            unit: String::new(),
+            points: vec![(-1.0, -1e308), (1.0, 1e308)],
+        };
+
+        assert!(render_plot(&plot).is_err());
+    }
+}
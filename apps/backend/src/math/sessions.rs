@@ -0,0 +1,455 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use numbat::markup::Markup;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serenity::all::UserId;
+use tokio::sync::Semaphore;
+
+use super::{
+    modules::{MAX_CODE_LENGTH, Modules},
+    plot::Plot,
+    units::Conversion,
+    worker::{Job, SentMarkup, Worker},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum EvaluationError {
+    /// Diagnostics reported by numbat.
+    #[error("{0}")]
+    Numbat(String),
+
+    #[error("evaluation timed out after {} seconds", .0.as_secs_f32())]
+    TimedOut(Duration),
+
+    #[error("input is too long, at most {0} characters are allowed")]
+    InputTooLong(usize),
+
+    #[error("too much was evaluated in this session, reset it to continue")]
+    SessionTooLarge,
+
+    #[error("another evaluation is still running in this session")]
+    Busy,
+
+    #[error("evaluation failed unexpectedly")]
+    Crashed,
+}
+
+/// Bounds for evaluations, which can otherwise run and allocate forever, e.g. with unbounded
+/// recursion.
+#[derive(Debug, Clone, Copy)]
+pub struct EvaluationLimits {
+    /// Wall-clock time an evaluation may take, including waiting for a free worker.
+    pub timeout: Duration,
+    pub max_input_length: usize,
+    /// Total length of all input evaluated in a session, numbat keeps every input around.
+    pub max_session_size: usize,
+    /// Number of evaluations running at the same time.
+    pub max_workers: usize,
+    /// Bytes the worker of a session may allocate, evaluations that need more crash.
+    pub max_memory: usize,
+}
+
+impl Default for EvaluationLimits {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(5),
+            max_input_length: 2000,
+            max_session_size: 64 * 1024,
+            max_workers: 4,
+            max_memory: 256 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Default)]
+struct SessionState {
+    /// Started by the first evaluation, which keeps the context of the session.
+    worker: Option<Worker>,
+    size: usize,
+}
+
+type SharedState = Arc<tokio::sync::Mutex<SessionState>>;
+
+struct Session {
+    state: SharedState,
+    last_used: Instant,
+}
+
+/// Variable or function defined in a session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Definition {
+    pub name: String,
+    /// Value of a variable or signature of a function.
+    pub value: String,
+}
+
+/// Per-user numbat contexts, so definitions persist between evaluations.
+///
+/// At most `capacity` sessions are kept, evicting the least recently used one, and sessions
+/// that were not used for `ttl` are dropped. Every session keeps a worker process, so
+/// `capacity` also bounds the number of processes.
+///
+/// Workers are started by running `program` with the [`super::WORKER_COMMAND`] subcommand,
+/// which has to call [`super::run_worker`].
+pub struct MathSessions {
+    sessions: Mutex<HashMap<UserId, Session>>,
+    capacity: usize,
+    ttl: Duration,
+    limits: EvaluationLimits,
+    workers: Semaphore,
+    program: PathBuf,
+}
+
+impl MathSessions {
+    pub fn new(
+        capacity: usize,
+        ttl: Duration,
+        limits: EvaluationLimits,
+        program: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            sessions: Mutex::new(HashMap::new()),
+            capacity,
+            ttl,
+            limits,
+            workers: Semaphore::new(limits.max_workers),
+            program: program.into(),
+        }
+    }
+
+    /// Evaluates `input` in the session of `user_id`, starting a new session if needed.
+    ///
+    /// Every session evaluates in its own worker process. numbat can not be interrupted, so the
+    /// worker of an evaluation that times out is killed, and its session is dropped so the user
+    /// can continue in a fresh one.
+    pub async fn evaluate(
+        &self,
+        user_id: UserId,
+        input: &str,
+        html: bool,
+    ) -> Result<String, EvaluationError> {
+        self.with_modules(Modules::default())
+            .evaluate(user_id, input, html)
+            .await
+    }
+
+    /// Like [`Self::evaluate`], but returns the result as markup, e.g. to render it as an image.
+    pub async fn evaluate_markup(
+        &self,
+        user_id: UserId,
+        input: &str,
+    ) -> Result<Markup, EvaluationError> {
+        self.with_modules(Modules::default())
+            .evaluate_markup(user_id, input)
+            .await
+    }
+
+    /// Samples `expression` as a function of `x` from `from` to `to`, using the definitions of
+    /// the session of `user_id` without changing the session.
+    pub async fn plot(
+        &self,
+        user_id: UserId,
+        expression: &str,
+        from: f64,
+        to: f64,
+    ) -> Result<Plot, EvaluationError> {
+        self.with_modules(Modules::default())
+            .plot(user_id, expression, from, to)
+            .await
+    }
+
+    /// Converts like [`super::UnitCatalogue::convert`], but in a worker started for it alone, so
+    /// the conversion is bounded by the limits of evaluations.
+    pub async fn convert(
+        &self,
+        value: f64,
+        from: &str,
+        to: &str,
+    ) -> Result<Conversion, EvaluationError> {
+        let job = Job::Convert {
+            value,
+            from: from.to_string(),
+            to: to.to_string(),
+        };
+
+        self.run_once(Modules::default(), job).await
+    }
+
+    /// Makes `modules` importable in addition to the builtin modules by the evaluations started
+    /// on the returned value.
+    pub fn with_modules(&self, modules: Modules) -> WithModules<'_> {
+        WithModules {
+            sessions: self,
+            modules,
+        }
+    }
+
+    /// Runs `job` in the session of `user_id`, growing the session by `size`.
+    async fn run<T: DeserializeOwned>(
+        &self,
+        user_id: UserId,
+        input: &str,
+        size: usize,
+        modules: Modules,
+        job: Job,
+    ) -> Result<T, EvaluationError> {
+        let max_input_length = self.limits.max_input_length;
+
+        if input.chars().count() > max_input_length {
+            return Err(EvaluationError::InputTooLong(max_input_length));
+        }
+
+        let state = self.state(user_id);
+
+        self.run_in(user_id, state, size, modules, job).await
+    }
+
+    /// Runs `job` by the worker of `state`, which is the session of `user_id`, starting the
+    /// worker if needed.
+    async fn run_in<T: DeserializeOwned>(
+        &self,
+        user_id: UserId,
+        state: SharedState,
+        size: usize,
+        modules: Modules,
+        job: Job,
+    ) -> Result<T, EvaluationError> {
+        let evaluation = async {
+            let mut state = state.try_lock().map_err(|_| EvaluationError::Busy)?;
+
+            if state.size + size > self.limits.max_session_size {
+                return Err(EvaluationError::SessionTooLarge);
+            }
+
+            let _permit = self
+                .workers
+                .acquire()
+                .await
+                .expect("worker semaphore is never closed");
+
+            let worker = match &mut state.worker {
+                Some(worker) => worker,
+                None => state.worker.insert(
+                    Worker::spawn(&self.program, self.limits.max_memory)
+                        .await
+                        .map_err(|_| EvaluationError::Crashed)?,
+                ),
+            };
+
+            let (result, imported) = worker
+                .run(job, modules)
+                .await
+                .map_err(|_| EvaluationError::Crashed)?;
+
+            // The context keeps the code of imported modules like the input
+            state.size += size + imported;
+            result.map_err(EvaluationError::Numbat)
+        };
+
+        let result = tokio::time::timeout(self.limits.timeout, evaluation)
+            .await
+            .unwrap_or(Err(EvaluationError::TimedOut(self.limits.timeout)));
+
+        if matches!(
+            result,
+            Err(EvaluationError::TimedOut(_) | EvaluationError::Crashed)
+        ) {
+            self.discard(user_id, &state);
+
+            // Stops an evaluation that is still running, the worker and its permit are free
+            // again once the evaluation above was dropped
+            if let Ok(mut state) = state.try_lock() {
+                state.worker = None;
+            }
+        }
+
+        result
+    }
+
+    /// Runs `job` by a worker started for it alone, so it neither waits for nor changes a
+    /// session.
+    async fn run_once<T: DeserializeOwned>(
+        &self,
+        modules: Modules,
+        job: Job,
+    ) -> Result<T, EvaluationError> {
+        let evaluation = async {
+            let _permit = self
+                .workers
+                .acquire()
+                .await
+                .expect("worker semaphore is never closed");
+
+            let mut worker = Worker::spawn(&self.program, self.limits.max_memory)
+                .await
+                .map_err(|_| EvaluationError::Crashed)?;
+
+            let (result, _) = worker
+                .run(job, modules)
+                .await
+                .map_err(|_| EvaluationError::Crashed)?;
+
+            result.map_err(EvaluationError::Numbat)
+        };
+
+        // The worker is killed when the evaluation is dropped
+        tokio::time::timeout(self.limits.timeout, evaluation)
+            .await
+            .unwrap_or(Err(EvaluationError::TimedOut(self.limits.timeout)))
+    }
+
+    /// Drops the session of `user_id`, returning whether there was one.
+    pub fn reset(&self, user_id: UserId) -> bool {
+        self.sessions.lock().unwrap().remove(&user_id).is_some()
+    }
+
+    /// Returns the variables and functions defined in the session of `user_id`, sorted by name.
+    pub async fn definitions(&self, user_id: UserId) -> Result<Vec<Definition>, EvaluationError> {
+        let Some(state) = self.existing_state(user_id) else {
+            return Ok(vec![]);
+        };
+
+        self.run_in(user_id, state, 0, Modules::default(), Job::Definitions)
+            .await
+    }
+
+    /// Drops the sessions that were not used for the time to live, which kills their workers.
+    /// Expired sessions are also dropped whenever a session is used.
+    pub fn expire(&self) {
+        self.remove_expired(&mut self.sessions.lock().unwrap());
+    }
+
+    fn existing_state(&self, user_id: UserId) -> Option<SharedState> {
+        let mut sessions = self.sessions.lock().unwrap();
+        self.remove_expired(&mut sessions);
+
+        let session = sessions.get_mut(&user_id)?;
+        session.last_used = Instant::now();
+
+        Some(session.state.clone())
+    }
+
+    fn state(&self, user_id: UserId) -> SharedState {
+        if let Some(state) = self.existing_state(user_id) {
+            return state;
+        }
+
+        let mut sessions = self.sessions.lock().unwrap();
+
+        if sessions.len() >= self.capacity
+            && let Some(oldest) = sessions
+                .iter()
+                .min_by_key(|(_, session)| session.last_used)
+                .map(|(user_id, _)| *user_id)
+        {
+            sessions.remove(&oldest);
+        }
+
+        sessions
+            .entry(user_id)
+            .or_insert_with(|| Session {
+                state: SharedState::default(),
+                last_used: Instant::now(),
+            })
+            .state
+            .clone()
+    }
+
+    /// Drops the session of `user_id` if it still is `state`.
+    fn discard(&self, user_id: UserId, state: &SharedState) {
+        let mut sessions = self.sessions.lock().unwrap();
+
+        if sessions
+            .get(&user_id)
+            .is_some_and(|session| Arc::ptr_eq(&session.state, state))
+        {
+            sessions.remove(&user_id);
+        }
+    }
+
+    fn remove_expired(&self, sessions: &mut HashMap<UserId, Session>) {
+        sessions.retain(|_, session| session.last_used.elapsed() < self.ttl);
+    }
+}
+
+/// Evaluations that can import modules in addition to the builtin ones, see
+/// [`MathSessions::with_modules`].
+pub struct WithModules<'a> {
+    sessions: &'a MathSessions,
+    modules: Modules,
+}
+
+impl WithModules<'_> {
+    /// Like [`MathSessions::evaluate`]. The code of imported modules counts towards the size of
+    /// the session.
+    pub async fn evaluate(
+        self,
+        user_id: UserId,
+        input: &str,
+        html: bool,
+    ) -> Result<String, EvaluationError> {
+        let job = Job::Evaluate {
+            input: input.to_string(),
+            html,
+        };
+
+        self.sessions
+            .run(user_id, input, input.len(), self.modules, job)
+            .await
+    }
+
+    /// Like [`MathSessions::evaluate_markup`].
+    pub async fn evaluate_markup(
+        self,
+        user_id: UserId,
+        input: &str,
+    ) -> Result<Markup, EvaluationError> {
+        let job = Job::EvaluateMarkup {
+            input: input.to_string(),
+        };
+
+        self.sessions
+            .run::<SentMarkup>(user_id, input, input.len(), self.modules, job)
+            .await
+            .map(SentMarkup::into)
+    }
+
+    /// Like [`MathSessions::plot`].
+    pub async fn plot(
+        self,
+        user_id: UserId,
+        expression: &str,
+        from: f64,
+        to: f64,
+    ) -> Result<Plot, EvaluationError> {
+        let job = Job::Plot {
+            expression: expression.to_string(),
+            from,
+            to,
+        };
+
+        self.sessions
+            .run(user_id, expression, 0, self.modules, job)
+            .await
+    }
+
+    /// Checks that `code` can be saved as a module by evaluating it in a fresh worker, so it
+    /// does not depend on the definitions of a session. Modules are limited to
+    /// [`MAX_CODE_LENGTH`] characters instead of the input length of evaluations.
+    pub async fn check_module(self, code: &str) -> Result<(), EvaluationError> {
+        if code.chars().count() > MAX_CODE_LENGTH {
+            return Err(EvaluationError::InputTooLong(MAX_CODE_LENGTH));
+        }
+
+        let job = Job::CheckModule {
+            code: code.to_string(),
+        };
+
+        self.sessions.run_once(self.modules, job).await
+    }
+}
@@ -0,0 +1,314 @@
+use numbat::{
+    InterpreterResult, InterpreterSettings, markup::Markup, resolver::CodeSource, value::Value,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{PRELUDE, diagnostics, prelude_context};
+
+/// Number of conversions into other units shown next to the requested one.
+const RELATED_COUNT: usize = 4;
+
+/// Prefixes numbat accepts in front of units, with their long name, short names and whether
+/// they are binary prefixes.
+const PREFIXES: &[(&str, &[&str], bool)] = &[
+    ("quecto", &["q"], false),
+    ("ronto", &["r"], false),
+    ("yocto", &["y"], false),
+    ("zepto", &["z"], false),
+    ("atto", &["a"], false),
+    ("femto", &["f"], false),
+    ("pico", &["p"], false),
+    ("nano", &["n"], false),
+    ("micro", &["µ", "μ", "u"], false),
+    ("milli", &["m"], false),
+    ("centi", &["c"], false),
+    ("deci", &["d"], false),
+    ("deca", &["da"], false),
+    ("hecto", &["h"], false),
+    ("kilo", &["k"], false),
+    ("mega", &["M"], false),
+    ("giga", &["G"], false),
+    ("tera", &["T"], false),
+    ("peta", &["P"], false),
+    ("exa", &["E"], false),
+    ("zetta", &["Z"], false),
+    ("yotta", &["Y"], false),
+    ("ronna", &["R"], false),
+    ("quetta", &["Q"], false),
+    ("kibi", &["Ki"], true),
+    ("mebi", &["Mi"], true),
+    ("gibi", &["Gi"], true),
+    ("tebi", &["Ti"], true),
+    ("pebi", &["Pi"], true),
+    ("exbi", &["Ei"], true),
+    ("zebi", &["Zi"], true),
+    ("yobi", &["Yi"], true),
+];
+
+#[derive(Debug, Clone)]
+struct Alias {
+    name: String,
+    short_prefixes: bool,
+    long_prefixes: bool,
+}
+
+/// Unit defined in the numbat prelude.
+#[derive(Debug, Clone)]
+pub struct Unit {
+    /// Name used in expressions, e.g. `m`.
+    pub symbol: String,
+    /// Readable name, e.g. `Metre`.
+    pub name: String,
+    /// Readable dimension, e.g. `Length`.
+    pub dimension: String,
+    /// Representation in base units, units can be converted into each other if it is equal.
+    base: String,
+    aliases: Vec<Alias>,
+    metric_prefixes: bool,
+    binary_prefixes: bool,
+}
+
+impl Unit {
+    fn matches(&self, query: &str) -> bool {
+        self.name.to_lowercase().contains(query)
+            || self
+                .aliases
+                .iter()
+                .any(|alias| alias.name.to_lowercase().contains(query))
+    }
+
+    /// Whether `name` refers to this unit, possibly with a prefix like `km` or `kilometre`.
+    fn accepts(&self, name: &str) -> bool {
+        self.aliases.iter().any(|alias| {
+            if alias.name == name {
+                return true;
+            }
+
+            let Some(prefix) = name.strip_suffix(alias.name.as_str()) else {
+                return false;
+            };
+
+            PREFIXES.iter().any(|(long, shorts, binary)| {
+                let allowed = if *binary {
+                    self.binary_prefixes
+                } else {
+                    self.metric_prefixes
+                };
+
+                allowed
+                    && ((alias.long_prefixes && prefix == *long)
+                        || (alias.short_prefixes && shorts.contains(&prefix)))
+            })
+        })
+    }
+
+    pub fn is_compatible(&self, other: &Unit) -> bool {
+        self.base == other.base
+    }
+
+    /// Whether this is a base unit like `m`, which all compatible units are defined in terms of.
+    fn is_base(&self) -> bool {
+        self.aliases.iter().any(|alias| alias.name == self.base)
+    }
+}
+
+/// Result of converting a value into another unit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conversion {
+    /// The converted value, e.g. `1.60934 km`.
+    pub result: String,
+    pub dimension: String,
+    /// The value in other units of the same dimension.
+    pub related: Vec<String>,
+}
+
+/// Units of the numbat prelude, to look up units and convert between compatible ones.
+pub struct UnitCatalogue {
+    units: Vec<Unit>,
+}
+
+impl Default for UnitCatalogue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UnitCatalogue {
+    pub fn new() -> Self {
+        let mut units: Vec<_> = PRELUDE
+            .unit_representations()
+            .map(|(name, (base, metadata))| Unit {
+                symbol: metadata.canonical_name.name.to_string(),
+                name: metadata
+                    .name
+                    .map(|name| name.to_string())
+                    .unwrap_or_else(|| name.to_string()),
+                dimension: metadata.readable_type.to_string(),
+                base: base.to_string(),
+                aliases: metadata
+                    .aliases
+                    .iter()
+                    .map(|(name, accepts_prefix)| Alias {
+                        name: name.to_string(),
+                        short_prefixes: accepts_prefix.short,
+                        long_prefixes: accepts_prefix.long,
+                    })
+                    .collect(),
+                metric_prefixes: metadata.metric_prefixes,
+                binary_prefixes: metadata.binary_prefixes,
+            })
+            .collect();
+
+        // The registry does not keep the units in a stable order
+        units.sort_by_cached_key(|unit| unit.name.to_lowercase());
+
+        Self { units }
+    }
+
+    /// Returns the units whose name or aliases contain `query`, in alphabetical order.
+    pub fn search(&self, query: &str) -> impl Iterator<Item = &Unit> {
+        let query = query.to_lowercase();
+
+        self.units.iter().filter(move |unit| unit.matches(&query))
+    }
+
+    /// Finds the unit `name` refers to, preferring exact names over prefixed ones so that e.g.
+    /// `min` is a minute and not a milli-inch.
+    pub fn resolve(&self, name: &str) -> Option<&Unit> {
+        let name = name.trim();
+
+        self.units
+            .iter()
+            .find(|unit| unit.aliases.iter().any(|alias| alias.name == name))
+            .or_else(|| self.units.iter().find(|unit| unit.accepts(name)))
+    }
+
+    /// Converts `value` from unit `from` into unit `to`, together with a few conversions into
+    /// other units of the same dimension. These start with the base unit, followed by the units
+    /// the value is closest to 1 in.
+    pub fn convert(&self, value: f64, from: &str, to: &str) -> Result<Conversion, String> {
+        let resolve = |name: &str| {
+            self.resolve(name)
+                .ok_or_else(|| format!("Unknown unit `{name}`"))
+        };
+
+        let (from, to) = (from.trim(), to.trim());
+        let (source, target) = (resolve(from)?, resolve(to)?);
+
+        if !source.is_compatible(target) {
+            return Err(format!(
+                "Can not convert {} ({}) into {} ({})",
+                source.name, source.dimension, target.name, target.dimension
+            ));
+        }
+
+        let mut context = prelude_context();
+        let (result, _) = convert_in(&mut context, value, from, to)?;
+
+        let mut related: Vec<_> = self
+            .units
+            .iter()
+            .filter(|unit| unit.is_compatible(source))
+            .filter(|unit| unit.symbol != source.symbol && unit.symbol != target.symbol)
+            .filter_map(|unit| {
+                let (result, value) = convert_in(&mut context, value, from, &unit.symbol).ok()?;
+                let distance = value.abs().log10().abs();
+
+                distance
+                    .is_finite()
+                    .then_some((!unit.is_base(), distance, result))
+            })
+            .collect();
+
+        related.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+
+        Ok(Conversion {
+            result,
+            dimension: source.dimension.clone(),
+            related: related
+                .into_iter()
+                .take(RELATED_COUNT)
+                .map(|(_, _, result)| result)
+                .collect(),
+        })
+    }
+}
+
+fn convert_in(
+    context: &mut numbat::Context,
+    value: f64,
+    from: &str,
+    to: &str,
+) -> Result<(String, f64), String> {
+    let mut settings = InterpreterSettings {
+        print_fn: Box::new(|_: &Markup| {}),
+    };
+
+    let (_, result) = context
+        .interpret_with_settings(
+            &mut settings,
+            &format!("{value} {from} -> {to}"),
+            CodeSource::Text,
+        )
+        .map_err(|error| diagnostics(context, *error, false))?;
+
+    match result {
+        InterpreterResult::Value(Value::Quantity(quantity)) => {
+            Ok((quantity.to_string(), quantity.unsafe_value().to_f64()))
+        }
+        _ => Err(format!("Failed to convert into `{to}`")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_units() {
+        let catalogue = UnitCatalogue::new();
+        let symbol = |name| catalogue.resolve(name).map(|unit| unit.symbol.as_str());
+
+        assert_eq!(symbol("m"), Some("m"));
+        assert_eq!(symbol("km"), Some("m"));
+        assert_eq!(symbol("kilometres"), Some("m"));
+        assert_eq!(symbol("min"), Some("min"));
+        assert_eq!(symbol("MiB"), Some("B"));
+        assert_eq!(symbol("kmetre"), None);
+        assert_eq!(symbol("parsec + 1"), None);
+    }
+
+    #[test]
+    fn searches_units() {
+        let catalogue = UnitCatalogue::new();
+        let names: Vec<_> = catalogue.search("MILE").map(|unit| &unit.name).collect();
+
+        assert!(names.contains(&&String::from("Mile")));
+        assert!(names.contains(&&String::from("Nautical Mile")));
+    }
+
+    #[test]
+    fn converts_units() {
+        let catalogue = UnitCatalogue::new();
+        let conversion = catalogue.convert(1.0, "mile", "ft").unwrap();
+
+        assert!(conversion.result.starts_with("5280 ft"), "{conversion:?}");
+        assert_eq!(conversion.dimension, "Length");
+        assert_eq!(conversion.related.len(), RELATED_COUNT);
+        assert!(
+            conversion.related[0].starts_with("1609.34 m"),
+            "{conversion:?}"
+        );
+    }
+
+    #[test]
+    fn rejects_incompatible_units() {
+        let catalogue = UnitCatalogue::new();
+
+        let error = catalogue.convert(1.0, "m", "s").unwrap_err();
+        assert_eq!(error, "Can not convert Metre (Length) into Second (Time)");
+
+        assert!(catalogue.convert(1.0, "m", "fib(40)").is_err());
+    }
+}
@@ -0,0 +1,361 @@
+use std::{
+    collections::HashSet,
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+    process::Stdio,
+    sync::LazyLock,
+};
+
+use numbat::{
+    compact_str::CompactString,
+    markup::{CompactStrCow, FormatType, FormattedString, Markup, OutputType},
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt},
+    process::{Child, ChildStdin, ChildStdout, Command},
+};
+
+use super::{
+    Evaluate,
+    modules::{self, Modules},
+    plot, prelude_context,
+    sessions::Definition,
+    units::UnitCatalogue,
+};
+
+/// Stack size of workers, numbat parses and type checks recursively so deeply nested input
+/// needs more than the default.
+const STACK_SIZE: usize = 16 * 1024 * 1024;
+
+/// Names of the variables and functions of the prelude, which are not listed as definitions.
+struct BuiltinNames {
+    variables: HashSet<CompactString>,
+    functions: HashSet<CompactString>,
+}
+
+static BUILTIN_NAMES: LazyLock<BuiltinNames> = LazyLock::new(|| {
+    let prelude = prelude_context();
+
+    BuiltinNames {
+        variables: prelude.variable_names().collect(),
+        functions: prelude.function_names().collect(),
+    }
+});
+
+static UNITS: LazyLock<UnitCatalogue> = LazyLock::new(UnitCatalogue::new);
+
+/// Work done by a worker on the context of its session.
+#[derive(Serialize, Deserialize)]
+pub(super) enum Job {
+    /// Results in a `String`.
+    Evaluate { input: String, html: bool },
+    /// Results in [`SentMarkup`].
+    EvaluateMarkup { input: String },
+    /// Results in a [`plot::Plot`], without changing the session.
+    Plot {
+        expression: String,
+        from: f64,
+        to: f64,
+    },
+    /// Results in `()`, evaluating `code` in a fresh context.
+    CheckModule { code: String },
+    /// Results in a list of [`Definition`]s sorted by name.
+    Definitions,
+    /// Results in a [`super::Conversion`], without using the session.
+    Convert {
+        value: f64,
+        from: String,
+        to: String,
+    },
+}
+
+impl Job {
+    /// Runs the job with the modules set by [`modules::with_modules`].
+    fn run(self, context: &mut numbat::Context) -> serde_json::Result<Response> {
+        match self {
+            Job::Evaluate { input, html } => {
+                Response::kept(context.evaluate_to_string(&input, html))
+            }
+            Job::EvaluateMarkup { input } => Response::kept(
+                context
+                    .evaluate_to_markup(&input, false)
+                    .map(SentMarkup::from),
+            ),
+            Job::Plot {
+                expression,
+                from,
+                to,
+            } => Response::discarded(plot::sample(&mut context.clone(), &expression, from, to)),
+            Job::CheckModule { code } => Response::discarded(
+                prelude_context()
+                    .evaluate_to_markup(&code, false)
+                    .map(|_| ()),
+            ),
+            Job::Definitions => Response::discarded(Ok(definitions(context.clone()))),
+            Job::Convert { value, from, to } => {
+                Response::discarded(UNITS.convert(value, &from, &to))
+            }
+        }
+    }
+}
+
+/// Lists the definitions of `context`. Every evaluation is kept as a source in the context, so
+/// the variables are evaluated on a copy to not grow the session.
+fn definitions(mut context: numbat::Context) -> Vec<Definition> {
+    let mut definitions: Vec<_> = context
+        .functions()
+        .filter(|(name, ..)| !BUILTIN_NAMES.functions.contains(name))
+        .map(|(name, _, signature, ..)| Definition {
+            name: name.to_string(),
+            value: signature.to_string(),
+        })
+        .collect();
+
+    let variables: Vec<_> = context
+        .variable_names()
+        .filter(|name| !BUILTIN_NAMES.variables.contains(name))
+        .collect();
+
+    for name in variables {
+        let value = context
+            .evaluate_to_string(&name, false)
+            .unwrap_or_else(|error| error);
+
+        definitions.push(Definition {
+            name: name.to_string(),
+            value,
+        });
+    }
+
+    definitions.sort_by(|a, b| a.name.cmp(&b.name));
+    definitions
+}
+
+#[derive(Serialize, Deserialize)]
+struct Request {
+    job: Job,
+    modules: Modules,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Response {
+    /// `Result` of the job, with the error reported by numbat.
+    result: serde_json::Value,
+    /// Length of the code of the modules imported into the context of the session.
+    imported: usize,
+}
+
+impl Response {
+    /// Response of a job that evaluated in the context of the session, which keeps the
+    /// imported modules.
+    fn kept<T: Serialize>(result: Result<T, String>) -> serde_json::Result<Self> {
+        Ok(Self {
+            result: serde_json::to_value(result)?,
+            imported: modules::imported_size(),
+        })
+    }
+
+    /// Response of a job that evaluated in a copy of the context or a fresh one.
+    fn discarded<T: Serialize>(result: Result<T, String>) -> serde_json::Result<Self> {
+        Ok(Self {
+            result: serde_json::to_value(result)?,
+            imported: 0,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "OutputType")]
+enum OutputTypeDef {
+    Normal,
+    Optional,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "FormatType")]
+enum FormatTypeDef {
+    Whitespace,
+    Emphasized,
+    Dimmed,
+    Text,
+    String,
+    Keyword,
+    Value,
+    Unit,
+    Identifier,
+    TypeIdentifier,
+    Operator,
+    Decorator,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SentString(
+    #[serde(with = "OutputTypeDef")] OutputType,
+    #[serde(with = "FormatTypeDef")] FormatType,
+    String,
+);
+
+/// [`Markup`] in a form that can be sent between processes.
+#[derive(Serialize, Deserialize)]
+pub(super) struct SentMarkup(Vec<SentString>);
+
+impl From<Markup> for SentMarkup {
+    fn from(markup: Markup) -> Self {
+        Self(
+            markup
+                .0
+                .into_iter()
+                .map(|FormattedString(output, format, text)| {
+                    SentString(output, format, text.to_string())
+                })
+                .collect(),
+        )
+    }
+}
+
+impl From<SentMarkup> for Markup {
+    fn from(markup: SentMarkup) -> Self {
+        Markup(
+            markup
+                .0
+                .into_iter()
+                .map(|SentString(output, format, text)| {
+                    FormattedString(output, format, CompactStrCow::Owned(text.into()))
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Subcommand of the bot that starts a worker with [`run_worker`].
+pub const WORKER_COMMAND: &str = "math-worker";
+
+/// Process that runs the jobs of one session. numbat can neither be interrupted nor limited in
+/// how much it allocates, so workers have a limited address space and are killed when dropped,
+/// which stops an evaluation that is still running.
+pub(super) struct Worker {
+    _process: Child,
+    requests: ChildStdin,
+    responses: tokio::io::BufReader<ChildStdout>,
+}
+
+impl Worker {
+    /// Starts a worker by running `program` with [`WORKER_COMMAND`], which may allocate
+    /// `max_memory` bytes in addition to what it needs to load the prelude.
+    pub(super) async fn spawn(program: &Path, max_memory: usize) -> io::Result<Self> {
+        let mut process = Command::new(program)
+            .arg(WORKER_COMMAND)
+            .arg("--max-memory")
+            .arg(max_memory.to_string())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let mut requests = process.stdin.take().ok_or(io::ErrorKind::BrokenPipe)?;
+        let responses = process.stdout.take().ok_or(io::ErrorKind::BrokenPipe)?;
+
+        // The exchange rates are only known to the bot, so they are the first line it sends
+        let mut setup = serde_json::to_vec(&super::exchange_rates())?;
+        setup.push(b'\n');
+        requests.write_all(&setup).await?;
+
+        Ok(Self {
+            _process: process,
+            requests,
+            responses: tokio::io::BufReader::new(responses),
+        })
+    }
+
+    /// Runs `job` with `modules` importable, returning its result or the error reported by
+    /// numbat, and the length of the code of the modules it imported into the session.
+    pub(super) async fn run<T: DeserializeOwned>(
+        &mut self,
+        job: Job,
+        modules: Modules,
+    ) -> io::Result<(Result<T, String>, usize)> {
+        let mut request = serde_json::to_vec(&Request { job, modules })?;
+        request.push(b'\n');
+        self.requests.write_all(&request).await?;
+
+        let mut line = String::new();
+
+        // The worker exited, e.g. because it ran out of memory
+        if self.responses.read_line(&mut line).await? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let response: Response = serde_json::from_str(&line)?;
+
+        Ok((serde_json::from_value(response.result)?, response.imported))
+    }
+}
+
+/// Runs the jobs the bot sends on stdin until it closes it, answering on stdout. This is the
+/// whole [`WORKER_COMMAND`], nothing else may write to stdout while it runs.
+pub fn run_worker(max_memory: usize) -> io::Result<()> {
+    let mut requests = BufReader::new(io::stdin());
+
+    let mut setup = String::new();
+    requests.read_line(&mut setup)?;
+
+    if let Some(xml) = serde_json::from_str::<Option<String>>(&setup)? {
+        super::install_exchange_rates(&xml);
+    }
+
+    LazyLock::force(&BUILTIN_NAMES);
+
+    std::thread::Builder::new()
+        .name(String::from(WORKER_COMMAND))
+        .stack_size(STACK_SIZE)
+        .spawn(move || serve(requests, max_memory))?
+        .join()
+        .map_err(|_| io::Error::other("math worker panicked"))?
+}
+
+fn serve(requests: impl BufRead, max_memory: usize) -> io::Result<()> {
+    // The stack of this thread and the prelude are allocated already
+    limit_memory(max_memory)?;
+
+    let mut context = prelude_context();
+    let mut responses = io::stdout().lock();
+
+    for line in requests.lines() {
+        let request: Request = serde_json::from_str(&line?)?;
+        let response = modules::with_modules(request.modules, || request.job.run(&mut context))?;
+
+        serde_json::to_writer(&mut responses, &response)?;
+        writeln!(responses)?;
+        responses.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Limits the address space to the one already used and `max_memory` bytes, so allocations
+/// beyond it abort the worker.
+fn limit_memory(max_memory: usize) -> io::Result<()> {
+    let statm = std::fs::read_to_string("/proc/self/statm")?;
+    let pages: u64 = statm
+        .split_whitespace()
+        .next()
+        .and_then(|pages| pages.parse().ok())
+        .ok_or(io::ErrorKind::InvalidData)?;
+
+    // SAFETY: `sysconf` has no preconditions
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u64;
+    let limit = pages * page_size + max_memory as u64;
+
+    let rlimit = libc::rlimit {
+        rlim_cur: limit,
+        rlim_max: limit,
+    };
+
+    // SAFETY: `rlimit` is a valid limit that outlives the call
+    if unsafe { libc::setrlimit(libc::RLIMIT_AS, &rlimit) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
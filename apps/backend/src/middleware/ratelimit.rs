@@ -66,15 +66,11 @@ impl KeyExtractor for JwtKeyExtractor {
 
                 match token_data {
                     Ok(token_data) => Ok(UserIpKey::from(token_data.sub)),
-                    Err(_) => SmartIpKeyExtractor
-                        .extract(req)
-                        .map(|ip| UserIpKey::from(ip)),
+                    Err(_) => SmartIpKeyExtractor.extract(req).map(UserIpKey::from),
                 }
             }
             None => {
-                let result = SmartIpKeyExtractor
-                    .extract(req)
-                    .map(|ip| UserIpKey::from(ip));
+                let result = SmartIpKeyExtractor.extract(req).map(UserIpKey::from);
                 tracing::warn!("result {:?}", result);
                 result
             }
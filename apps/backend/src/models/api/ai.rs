@@ -4,7 +4,7 @@ use reqwest::Url;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum GenerateTextMessageRole {
     User,
@@ -13,7 +13,7 @@ pub enum GenerateTextMessageRole {
     Tool,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GenerateTextMessage {
     role: GenerateTextMessageRole,
@@ -27,6 +27,10 @@ impl GenerateTextMessage {
             content: content.into(),
         }
     }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
 }
 
 #[skip_serializing_none]
@@ -3,7 +3,7 @@
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[skip_serializing_none]
 pub struct Language {
     pub language: String,
@@ -14,7 +14,7 @@ pub struct Language {
 
 pub type LanguagesResponse = Vec<Language>;
 
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq, Eq)]
 #[skip_serializing_none]
 pub struct ExecuteFile {
     name: Option<String>,
@@ -113,7 +113,7 @@ impl ExecuteRequest {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct ExecuteStage {
     pub stdout: String,
     pub stderr: String,
@@ -122,7 +122,7 @@ pub struct ExecuteStage {
     pub signal: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct ExecuteResponse {
     pub language: String,
     pub version: String,
@@ -5,6 +5,9 @@ pub enum CustomIdError {
 
     #[error("custom id length {0} exceeds maximum of 100")]
     TooLong(usize),
+
+    #[error("custom id data {0:?} contains the separator")]
+    Separator(String),
 }
 
 pub struct CustomId {
@@ -45,7 +48,7 @@ impl TryFrom<String> for CustomId {
     fn try_from(value: String) -> Result<Self, Self::Error> {
         let data: Vec<_> = value.split_terminator(",").collect();
 
-        let id = data.get(0).ok_or(CustomIdError::Parse)?.to_string();
+        let id = data.first().ok_or(CustomIdError::Parse)?.to_string();
         let data: Vec<_> = data[1..].iter().map(|d| d.to_string()).collect();
 
         Ok(Self { id, data })
@@ -56,23 +59,28 @@ impl TryFrom<CustomId> for String {
     type Error = CustomIdError;
 
     fn try_from(value: CustomId) -> Result<Self, Self::Error> {
-        let serialized_len: usize =
-            value.data.iter().map(|s| s.len()).sum::<usize>() + value.id.len();
+        // Data with a separator would be read back as several segments
+        if let Some(data) = value.data.iter().find(|data| data.contains(',')) {
+            return Err(CustomIdError::Separator(data.clone()));
+        }
+
+        let serialized = format!(
+            "{}{}{}",
+            value.id,
+            if !value.data.is_empty() { "," } else { "" },
+            value.data.join(",")
+        );
 
-        if serialized_len > 100 {
-            Err(CustomIdError::TooLong(serialized_len))
+        if serialized.len() > 100 {
+            Err(CustomIdError::TooLong(serialized.len()))
         } else {
-            Ok(format!(
-                "{}{}{}",
-                value.id,
-                if !value.data.is_empty() { "," } else { "" },
-                value.data.join(",")
-            ))
+            Ok(serialized)
         }
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::len_zero)]
 mod tests {
     use super::*;
 
@@ -119,4 +127,26 @@ mod tests {
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), "example,data1,data2".to_string())
     }
+
+    #[test]
+    fn into_string_rejects_separator() {
+        let custom_id = CustomId {
+            id: String::from("example"),
+            data: vec![String::from("data1,data2")],
+        };
+        let result = String::try_from(custom_id);
+
+        assert!(matches!(result, Err(CustomIdError::Separator(_))));
+    }
+
+    #[test]
+    fn into_string_counts_separators() {
+        let custom_id = CustomId {
+            id: String::from("example"),
+            data: vec![String::from("a"); 47],
+        };
+        let result = String::try_from(custom_id);
+
+        assert!(matches!(result, Err(CustomIdError::TooLong(101))));
+    }
 }
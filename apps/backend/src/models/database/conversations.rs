@@ -0,0 +1,304 @@
+use chrono::{DateTime, Utc};
+use serenity::all::{ChannelId, UserId};
+
+use crate::error::Error;
+
+use super::Database;
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Conversation {
+    pub id: i64,
+    #[allow(dead_code)]
+    channel_id: i64,
+    /// User that started the conversation.
+    #[allow(dead_code)]
+    user_id: i64,
+    /// Summary of the messages that were removed from the history.
+    pub summary: Option<String>,
+    pub tokens_used: i64,
+    #[allow(dead_code)]
+    pub created_at: DateTime<Utc>,
+    #[allow(dead_code)]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(rename_all = "snake_case")]
+pub enum MessageRole {
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ConversationMessage {
+    pub id: i64,
+    #[allow(dead_code)]
+    pub conversation_id: i64,
+    pub role: MessageRole,
+    pub content: String,
+    #[allow(dead_code)]
+    pub created_at: DateTime<Utc>,
+}
+
+pub trait ConversationRepository {
+    async fn create_conversation(
+        &self,
+        channel_id: ChannelId,
+        user_id: UserId,
+    ) -> Result<Conversation, Error>;
+
+    async fn get_conversation(&self, conversation_id: i64) -> Result<Option<Conversation>, Error>;
+
+    /// Returns the conversation in `channel_id` that was continued last.
+    async fn latest_conversation(
+        &self,
+        channel_id: ChannelId,
+    ) -> Result<Option<Conversation>, Error>;
+
+    /// Returns the messages of the history of a conversation, oldest first.
+    async fn conversation_messages(
+        &self,
+        conversation_id: i64,
+    ) -> Result<Vec<ConversationMessage>, Error>;
+
+    /// Appends a prompt and the reply to it, which took `tokens` to generate.
+    async fn add_exchange(
+        &self,
+        conversation_id: i64,
+        prompt: &str,
+        reply: &str,
+        tokens: i64,
+    ) -> Result<(), Error>;
+
+    /// Replaces the messages up to and including `last_message_id` with `summary`, which took
+    /// `tokens` to generate.
+    async fn summarize_conversation(
+        &self,
+        conversation_id: i64,
+        summary: &str,
+        last_message_id: i64,
+        tokens: i64,
+    ) -> Result<(), Error>;
+}
+
+impl ConversationRepository for Database {
+    async fn create_conversation(
+        &self,
+        channel_id: ChannelId,
+        user_id: UserId,
+    ) -> Result<Conversation, Error> {
+        let now = Utc::now();
+
+        let conversation = sqlx::query_as::<_, Conversation>(
+            "INSERT INTO ai_conversations (channel_id, user_id, created_at, updated_at)
+             VALUES (?, ?, ?, ?)
+             RETURNING *",
+        )
+        .bind(channel_id.get() as i64)
+        .bind(user_id.get() as i64)
+        .bind(now)
+        .bind(now)
+        .fetch_one(self.pool())
+        .await?;
+
+        Ok(conversation)
+    }
+
+    async fn get_conversation(&self, conversation_id: i64) -> Result<Option<Conversation>, Error> {
+        let conversation =
+            sqlx::query_as::<_, Conversation>("SELECT * FROM ai_conversations WHERE id = ?")
+                .bind(conversation_id)
+                .fetch_optional(self.pool())
+                .await?;
+
+        Ok(conversation)
+    }
+
+    async fn latest_conversation(
+        &self,
+        channel_id: ChannelId,
+    ) -> Result<Option<Conversation>, Error> {
+        let conversation = sqlx::query_as::<_, Conversation>(
+            "SELECT * FROM ai_conversations WHERE channel_id = ?
+             ORDER BY updated_at DESC, id DESC
+             LIMIT 1",
+        )
+        .bind(channel_id.get() as i64)
+        .fetch_optional(self.pool())
+        .await?;
+
+        Ok(conversation)
+    }
+
+    async fn conversation_messages(
+        &self,
+        conversation_id: i64,
+    ) -> Result<Vec<ConversationMessage>, Error> {
+        let messages = sqlx::query_as::<_, ConversationMessage>(
+            "SELECT * FROM ai_messages WHERE conversation_id = ? ORDER BY id",
+        )
+        .bind(conversation_id)
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(messages)
+    }
+
+    async fn add_exchange(
+        &self,
+        conversation_id: i64,
+        prompt: &str,
+        reply: &str,
+        tokens: i64,
+    ) -> Result<(), Error> {
+        let now = Utc::now();
+        let mut transaction = self.pool().begin().await?;
+
+        for (role, content) in [(MessageRole::User, prompt), (MessageRole::Assistant, reply)] {
+            sqlx::query(
+                "INSERT INTO ai_messages (conversation_id, role, content, created_at)
+                 VALUES (?, ?, ?, ?)",
+            )
+            .bind(conversation_id)
+            .bind(role)
+            .bind(content)
+            .bind(now)
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        sqlx::query(
+            "UPDATE ai_conversations SET tokens_used = tokens_used + ?, updated_at = ?
+             WHERE id = ?",
+        )
+        .bind(tokens)
+        .bind(now)
+        .bind(conversation_id)
+        .execute(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn summarize_conversation(
+        &self,
+        conversation_id: i64,
+        summary: &str,
+        last_message_id: i64,
+        tokens: i64,
+    ) -> Result<(), Error> {
+        let mut transaction = self.pool().begin().await?;
+
+        sqlx::query("DELETE FROM ai_messages WHERE conversation_id = ? AND id <= ?")
+            .bind(conversation_id)
+            .bind(last_message_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        sqlx::query(
+            "UPDATE ai_conversations SET summary = ?, tokens_used = tokens_used + ? WHERE id = ?",
+        )
+        .bind(summary)
+        .bind(tokens)
+        .bind(conversation_id)
+        .execute(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn finds_latest_conversation() {
+        let database = Database::test().await;
+        let channel_id = ChannelId::new(1);
+
+        assert!(
+            database
+                .latest_conversation(channel_id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let first = database
+            .create_conversation(channel_id, UserId::new(2))
+            .await
+            .unwrap();
+        let second = database
+            .create_conversation(channel_id, UserId::new(3))
+            .await
+            .unwrap();
+        database
+            .create_conversation(ChannelId::new(4), UserId::new(2))
+            .await
+            .unwrap();
+
+        let latest = database.latest_conversation(channel_id).await.unwrap();
+        assert_eq!(latest.unwrap().id, second.id);
+
+        // Continuing a conversation makes it the latest one again
+        database
+            .add_exchange(first.id, "hi", "hello", 10)
+            .await
+            .unwrap();
+
+        let latest = database.latest_conversation(channel_id).await.unwrap();
+        assert_eq!(latest.unwrap().id, first.id);
+    }
+
+    #[tokio::test]
+    async fn stores_and_summarizes_messages() {
+        let database = Database::test().await;
+        let conversation = database
+            .create_conversation(ChannelId::new(1), UserId::new(2))
+            .await
+            .unwrap();
+
+        database
+            .add_exchange(conversation.id, "a", "b", 10)
+            .await
+            .unwrap();
+        database
+            .add_exchange(conversation.id, "c", "d", 20)
+            .await
+            .unwrap();
+
+        let messages = database
+            .conversation_messages(conversation.id)
+            .await
+            .unwrap();
+        let contents: Vec<_> = messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, ["a", "b", "c", "d"]);
+        assert_eq!(messages[0].role, MessageRole::User);
+        assert_eq!(messages[1].role, MessageRole::Assistant);
+
+        database
+            .summarize_conversation(conversation.id, "a and b", messages[1].id, 5)
+            .await
+            .unwrap();
+
+        let conversation = database
+            .get_conversation(conversation.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(conversation.summary.as_deref(), Some("a and b"));
+        assert_eq!(conversation.tokens_used, 35);
+
+        let messages = database
+            .conversation_messages(conversation.id)
+            .await
+            .unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].content, "c");
+    }
+}
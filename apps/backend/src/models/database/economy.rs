@@ -0,0 +1,366 @@
+use chrono::{DateTime, Duration, Utc};
+use serenity::all::UserId;
+use sqlx::SqliteConnection;
+
+use crate::error::Error;
+
+use super::Database;
+
+#[derive(thiserror::Error, Debug)]
+pub enum EconomyError {
+    #[error("amount must be positive")]
+    InvalidAmount,
+
+    #[error("insufficient funds")]
+    InsufficientFunds,
+
+    #[error("can not transfer to yourself")]
+    SelfTransfer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(rename_all = "snake_case")]
+pub enum LedgerReason {
+    Daily,
+    Transfer,
+    Bet,
+    Payout,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Wallet {
+    user_id: i64,
+    pub balance: i64,
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub last_daily_at: Option<DateTime<Utc>>,
+}
+
+impl Wallet {
+    pub fn user_id(&self) -> UserId {
+        UserId::new(self.user_id as u64)
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct LedgerEntry {
+    #[allow(dead_code)]
+    pub id: i64,
+    #[allow(dead_code)]
+    user_id: i64,
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub amount: i64,
+    /// Balance of the user after the change was applied.
+    #[allow(dead_code)]
+    pub balance: i64,
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub reason: LedgerReason,
+    #[cfg_attr(not(test), allow(dead_code))]
+    counterparty_id: Option<i64>,
+    #[allow(dead_code)]
+    pub created_at: DateTime<Utc>,
+}
+
+impl LedgerEntry {
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn counterparty_id(&self) -> Option<UserId> {
+        self.counterparty_id.map(|id| UserId::new(id as u64))
+    }
+}
+
+pub enum DailyClaim {
+    Claimed { balance: i64 },
+    OnCooldown { available_at: DateTime<Utc> },
+}
+
+pub trait EconomyRepository {
+    /// Returns the wallet of the user, which is empty if the user never received anything.
+    async fn get_wallet(&self, user_id: UserId) -> Result<Wallet, Error>;
+
+    /// Pays out `amount` if the last claim was at least `cooldown` before `now`.
+    async fn claim_daily(
+        &self,
+        user_id: UserId,
+        amount: i64,
+        cooldown: Duration,
+        now: DateTime<Utc>,
+    ) -> Result<DailyClaim, Error>;
+
+    /// Moves `amount` between two wallets, returning the new balances of both users.
+    ///
+    /// Fails with [`EconomyError::InsufficientFunds`] without changing anything if the sender
+    /// can not cover the amount.
+    async fn transfer(&self, from: UserId, to: UserId, amount: i64) -> Result<(i64, i64), Error>;
+
+    /// Returns the wallets with the highest balances in descending order.
+    async fn leaderboard(&self, limit: i64) -> Result<Vec<Wallet>, Error>;
+
+    /// Returns the most recent balance changes of the user, newest first. Only read by hand to
+    /// investigate disputes for now.
+    #[cfg_attr(not(test), allow(dead_code))]
+    async fn get_ledger(&self, user_id: UserId, limit: i64) -> Result<Vec<LedgerEntry>, Error>;
+}
+
+/// Adds `amount` (which may be negative) to the balance of the user and records it in the
+/// ledger, failing if the balance would drop below zero. Returns the new balance.
+pub(super) async fn apply_change(
+    connection: &mut SqliteConnection,
+    user_id: UserId,
+    amount: i64,
+    reason: LedgerReason,
+    counterparty_id: Option<UserId>,
+) -> Result<i64, Error> {
+    let user_id = user_id.get() as i64;
+
+    sqlx::query("INSERT INTO wallets (user_id) VALUES (?) ON CONFLICT (user_id) DO NOTHING")
+        .bind(user_id)
+        .execute(&mut *connection)
+        .await?;
+
+    let balance: Option<i64> = sqlx::query_scalar(
+        "UPDATE wallets SET balance = balance + ?1
+         WHERE user_id = ?2 AND balance + ?1 >= 0
+         RETURNING balance",
+    )
+    .bind(amount)
+    .bind(user_id)
+    .fetch_optional(&mut *connection)
+    .await?;
+
+    let balance = balance.ok_or(EconomyError::InsufficientFunds)?;
+
+    sqlx::query(
+        "INSERT INTO ledger (user_id, amount, balance, reason, counterparty_id, created_at)
+         VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(user_id)
+    .bind(amount)
+    .bind(balance)
+    .bind(reason)
+    .bind(counterparty_id.map(|id| id.get() as i64))
+    .bind(Utc::now())
+    .execute(&mut *connection)
+    .await?;
+
+    Ok(balance)
+}
+
+impl EconomyRepository for Database {
+    async fn get_wallet(&self, user_id: UserId) -> Result<Wallet, Error> {
+        let wallet = sqlx::query_as::<_, Wallet>("SELECT * FROM wallets WHERE user_id = ?")
+            .bind(user_id.get() as i64)
+            .fetch_optional(self.pool())
+            .await?;
+
+        Ok(wallet.unwrap_or(Wallet {
+            user_id: user_id.get() as i64,
+            balance: 0,
+            last_daily_at: None,
+        }))
+    }
+
+    async fn claim_daily(
+        &self,
+        user_id: UserId,
+        amount: i64,
+        cooldown: Duration,
+        now: DateTime<Utc>,
+    ) -> Result<DailyClaim, Error> {
+        let mut transaction = self.pool().begin().await?;
+
+        sqlx::query("INSERT INTO wallets (user_id) VALUES (?) ON CONFLICT (user_id) DO NOTHING")
+            .bind(user_id.get() as i64)
+            .execute(&mut *transaction)
+            .await?;
+
+        let last_daily_at: Option<DateTime<Utc>> =
+            sqlx::query_scalar("SELECT last_daily_at FROM wallets WHERE user_id = ?")
+                .bind(user_id.get() as i64)
+                .fetch_one(&mut *transaction)
+                .await?;
+
+        if let Some(last_daily_at) = last_daily_at
+            && now < last_daily_at + cooldown
+        {
+            return Ok(DailyClaim::OnCooldown {
+                available_at: last_daily_at + cooldown,
+            });
+        }
+
+        // Only succeeds if no concurrent claim changed the timestamp since it was read
+        let updated = sqlx::query(
+            "UPDATE wallets SET last_daily_at = ? WHERE user_id = ? AND last_daily_at IS ?",
+        )
+        .bind(now)
+        .bind(user_id.get() as i64)
+        .bind(last_daily_at)
+        .execute(&mut *transaction)
+        .await?;
+
+        if updated.rows_affected() == 0 {
+            return Ok(DailyClaim::OnCooldown {
+                available_at: now + cooldown,
+            });
+        }
+
+        let balance =
+            apply_change(&mut transaction, user_id, amount, LedgerReason::Daily, None).await?;
+
+        transaction.commit().await?;
+
+        Ok(DailyClaim::Claimed { balance })
+    }
+
+    async fn transfer(&self, from: UserId, to: UserId, amount: i64) -> Result<(i64, i64), Error> {
+        if amount <= 0 {
+            return Err(EconomyError::InvalidAmount.into());
+        }
+
+        if from == to {
+            return Err(EconomyError::SelfTransfer.into());
+        }
+
+        let mut transaction = self.pool().begin().await?;
+
+        let from_balance = apply_change(
+            &mut transaction,
+            from,
+            -amount,
+            LedgerReason::Transfer,
+            Some(to),
+        )
+        .await?;
+        let to_balance = apply_change(
+            &mut transaction,
+            to,
+            amount,
+            LedgerReason::Transfer,
+            Some(from),
+        )
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok((from_balance, to_balance))
+    }
+
+    async fn leaderboard(&self, limit: i64) -> Result<Vec<Wallet>, Error> {
+        let wallets = sqlx::query_as::<_, Wallet>(
+            "SELECT * FROM wallets WHERE balance > 0 ORDER BY balance DESC, user_id LIMIT ?",
+        )
+        .bind(limit)
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(wallets)
+    }
+
+    async fn get_ledger(&self, user_id: UserId, limit: i64) -> Result<Vec<LedgerEntry>, Error> {
+        let entries = sqlx::query_as::<_, LedgerEntry>(
+            "SELECT * FROM ledger WHERE user_id = ? ORDER BY id DESC LIMIT ?",
+        )
+        .bind(user_id.get() as i64)
+        .bind(limit)
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn empty_wallet() {
+        let database = Database::test().await;
+        let wallet = database.get_wallet(UserId::new(1)).await.unwrap();
+
+        assert_eq!(wallet.balance, 0);
+        assert!(wallet.last_daily_at.is_none());
+    }
+
+    #[tokio::test]
+    async fn daily_cooldown() {
+        let database = Database::test().await;
+        let user_id = UserId::new(1);
+        let cooldown = Duration::hours(24);
+        let now = Utc::now();
+
+        let claim = database.claim_daily(user_id, 100, cooldown, now).await;
+        assert!(matches!(claim, Ok(DailyClaim::Claimed { balance: 100 })));
+
+        let claim = database
+            .claim_daily(user_id, 100, cooldown, now + Duration::hours(1))
+            .await;
+        assert!(
+            matches!(claim, Ok(DailyClaim::OnCooldown { available_at }) if available_at == now + cooldown)
+        );
+
+        let claim = database
+            .claim_daily(user_id, 100, cooldown, now + cooldown)
+            .await;
+        assert!(matches!(claim, Ok(DailyClaim::Claimed { balance: 200 })));
+    }
+
+    #[tokio::test]
+    async fn transfer_moves_balance() {
+        let database = Database::test().await;
+        let (alice, bob) = (UserId::new(1), UserId::new(2));
+
+        database
+            .claim_daily(alice, 100, Duration::hours(24), Utc::now())
+            .await
+            .unwrap();
+
+        let balances = database.transfer(alice, bob, 30).await.unwrap();
+        assert_eq!(balances, (70, 30));
+
+        let ledger = database.get_ledger(alice, 10).await.unwrap();
+        assert_eq!(ledger.len(), 2);
+        assert_eq!(ledger[0].amount, -30);
+        assert_eq!(ledger[0].reason, LedgerReason::Transfer);
+        assert_eq!(ledger[0].counterparty_id(), Some(bob));
+    }
+
+    #[tokio::test]
+    async fn transfer_is_atomic() {
+        let database = Database::test().await;
+        let (alice, bob) = (UserId::new(1), UserId::new(2));
+
+        let result = database.transfer(alice, bob, 30).await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref(),
+            Some(EconomyError::InsufficientFunds)
+        ));
+
+        assert_eq!(database.get_wallet(bob).await.unwrap().balance, 0);
+        assert!(database.get_ledger(bob, 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn transfer_rejects_invalid() {
+        let database = Database::test().await;
+        let alice = UserId::new(1);
+
+        assert!(database.transfer(alice, UserId::new(2), 0).await.is_err());
+        assert!(database.transfer(alice, alice, 10).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn leaderboard_order() {
+        let database = Database::test().await;
+        let now = Utc::now();
+
+        for (id, amount) in [(1, 50), (2, 150), (3, 100)] {
+            database
+                .claim_daily(UserId::new(id), amount, Duration::hours(24), now)
+                .await
+                .unwrap();
+        }
+
+        let leaderboard = database.leaderboard(2).await.unwrap();
+        let ids: Vec<_> = leaderboard.iter().map(|w| w.user_id().get()).collect();
+        assert_eq!(ids, vec![2, 3]);
+    }
+}
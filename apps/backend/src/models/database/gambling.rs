@@ -0,0 +1,263 @@
+use chrono::{DateTime, Utc};
+use serenity::all::UserId;
+use sqlx::types::Json;
+
+use crate::{error::Error, games::blackjack::Blackjack};
+
+use super::{
+    Database,
+    economy::{LedgerReason, apply_change},
+};
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct BlackjackGame {
+    pub id: i64,
+    user_id: i64,
+    pub state: Json<Blackjack>,
+    #[allow(dead_code)]
+    pub created_at: DateTime<Utc>,
+    #[allow(dead_code)]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl BlackjackGame {
+    pub fn user_id(&self) -> UserId {
+        UserId::new(self.user_id as u64)
+    }
+}
+
+pub trait GamblingRepository {
+    /// Takes the bet and pays out `payout` in a single transaction, returning the new balance.
+    ///
+    /// Fails without changing anything if the user can not cover the bet.
+    async fn play_round(&self, user_id: UserId, bet: i64, payout: i64) -> Result<i64, Error>;
+
+    /// Takes the bet of the game into escrow and stores the game, returning its ID. Games that
+    /// are already finished are paid out immediately.
+    async fn create_blackjack_game(&self, user_id: UserId, game: &Blackjack) -> Result<i64, Error>;
+
+    async fn get_blackjack_game(&self, game_id: i64) -> Result<Option<BlackjackGame>, Error>;
+
+    /// Replaces the state of a running game, paying out the bet if the new state is finished.
+    ///
+    /// Returns `false` without changing anything if the game was updated since `previous` was
+    /// read, e.g. when a button was clicked twice.
+    async fn update_blackjack_game(
+        &self,
+        previous: &BlackjackGame,
+        game: &Blackjack,
+    ) -> Result<bool, Error>;
+}
+
+impl GamblingRepository for Database {
+    async fn play_round(&self, user_id: UserId, bet: i64, payout: i64) -> Result<i64, Error> {
+        let mut transaction = self.pool().begin().await?;
+
+        let mut balance =
+            apply_change(&mut transaction, user_id, -bet, LedgerReason::Bet, None).await?;
+
+        if payout > 0 {
+            balance = apply_change(
+                &mut transaction,
+                user_id,
+                payout,
+                LedgerReason::Payout,
+                None,
+            )
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(balance)
+    }
+
+    async fn create_blackjack_game(&self, user_id: UserId, game: &Blackjack) -> Result<i64, Error> {
+        let mut transaction = self.pool().begin().await?;
+
+        apply_change(
+            &mut transaction,
+            user_id,
+            -game.bet,
+            LedgerReason::Bet,
+            None,
+        )
+        .await?;
+
+        let now = Utc::now();
+        let finished_at = game.is_finished().then_some(now);
+
+        let game_id: i64 = sqlx::query_scalar(
+            "INSERT INTO blackjack_games (user_id, state, created_at, finished_at)
+             VALUES (?, ?, ?, ?)
+             RETURNING id",
+        )
+        .bind(user_id.get() as i64)
+        .bind(Json(game))
+        .bind(now)
+        .bind(finished_at)
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        if let Some(outcome) = game.outcome()
+            && outcome.payout(game.bet) > 0
+        {
+            apply_change(
+                &mut transaction,
+                user_id,
+                outcome.payout(game.bet),
+                LedgerReason::Payout,
+                None,
+            )
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(game_id)
+    }
+
+    async fn get_blackjack_game(&self, game_id: i64) -> Result<Option<BlackjackGame>, Error> {
+        let game = sqlx::query_as::<_, BlackjackGame>("SELECT * FROM blackjack_games WHERE id = ?")
+            .bind(game_id)
+            .fetch_optional(self.pool())
+            .await?;
+
+        Ok(game)
+    }
+
+    async fn update_blackjack_game(
+        &self,
+        previous: &BlackjackGame,
+        game: &Blackjack,
+    ) -> Result<bool, Error> {
+        let mut transaction = self.pool().begin().await?;
+
+        let finished_at = game.is_finished().then(Utc::now);
+
+        let updated = sqlx::query(
+            "UPDATE blackjack_games SET state = ?, finished_at = ?
+             WHERE id = ? AND state = ? AND finished_at IS NULL",
+        )
+        .bind(Json(game))
+        .bind(finished_at)
+        .bind(previous.id)
+        .bind(&previous.state)
+        .execute(&mut *transaction)
+        .await?;
+
+        if updated.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        if let Some(outcome) = game.outcome()
+            && outcome.payout(game.bet) > 0
+        {
+            apply_change(
+                &mut transaction,
+                previous.user_id(),
+                outcome.payout(game.bet),
+                LedgerReason::Payout,
+                None,
+            )
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use crate::{
+        games::blackjack::{Card, Suit},
+        models::database::economy::{EconomyError, EconomyRepository},
+    };
+
+    use super::*;
+
+    async fn database_with_balance(user_id: UserId, balance: i64) -> Database {
+        let database = Database::test().await;
+        database
+            .claim_daily(user_id, balance, Duration::hours(24), Utc::now())
+            .await
+            .unwrap();
+        database
+    }
+
+    #[tokio::test]
+    async fn round_requires_funds() {
+        let user_id = UserId::new(1);
+        let database = database_with_balance(user_id, 10).await;
+
+        let result = database.play_round(user_id, 20, 40).await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref(),
+            Some(EconomyError::InsufficientFunds)
+        ));
+        assert_eq!(database.get_wallet(user_id).await.unwrap().balance, 10);
+
+        assert_eq!(database.play_round(user_id, 10, 20).await.unwrap(), 20);
+    }
+
+    #[tokio::test]
+    async fn blackjack_escrow_and_payout() {
+        let user_id = UserId::new(1);
+        let database = database_with_balance(user_id, 100).await;
+
+        // Player gets 10 + 9, dealer gets 10 + 7
+        let deck = [7, 10, 9, 10]
+            .map(|rank| Card::new(rank, Suit::Hearts))
+            .to_vec();
+        let game = Blackjack::from_deck(40, deck);
+
+        let game_id = database
+            .create_blackjack_game(user_id, &game)
+            .await
+            .unwrap();
+        assert_eq!(database.get_wallet(user_id).await.unwrap().balance, 60);
+
+        let stored = database.get_blackjack_game(game_id).await.unwrap().unwrap();
+        assert_eq!(stored.user_id(), user_id);
+
+        let mut finished = stored.state.0.clone();
+        finished.stand();
+
+        assert!(
+            database
+                .update_blackjack_game(&stored, &finished)
+                .await
+                .unwrap()
+        );
+        assert_eq!(database.get_wallet(user_id).await.unwrap().balance, 140);
+
+        // A second click on the same state must not pay out again
+        assert!(
+            !database
+                .update_blackjack_game(&stored, &finished)
+                .await
+                .unwrap()
+        );
+        assert_eq!(database.get_wallet(user_id).await.unwrap().balance, 140);
+    }
+
+    #[tokio::test]
+    async fn blackjack_requires_funds() {
+        let user_id = UserId::new(1);
+        let database = database_with_balance(user_id, 10).await;
+
+        let game = Blackjack::new(20, &mut StdRng::seed_from_u64(1));
+        assert!(
+            database
+                .create_blackjack_game(user_id, &game)
+                .await
+                .is_err()
+        );
+        assert!(database.get_blackjack_game(1).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,311 @@
+use chrono::{DateTime, Utc};
+use serenity::all::{GuildId, UserId};
+
+use crate::{error::Error, math::Modules};
+
+use super::Database;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(rename_all = "snake_case")]
+pub enum ModuleScope {
+    User,
+    Guild,
+}
+
+/// Who a module belongs to, only the owner can import it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleOwner {
+    User(UserId),
+    /// Shared with all members of the guild.
+    Guild(GuildId),
+}
+
+impl ModuleOwner {
+    fn scope(&self) -> ModuleScope {
+        match self {
+            Self::User(_) => ModuleScope::User,
+            Self::Guild(_) => ModuleScope::Guild,
+        }
+    }
+
+    fn id(&self) -> i64 {
+        match self {
+            Self::User(user_id) => user_id.get() as i64,
+            Self::Guild(guild_id) => guild_id.get() as i64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct MathModule {
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub id: i64,
+    scope: ModuleScope,
+    owner_id: i64,
+    /// Path the module is imported by, e.g. `team::constants`.
+    pub name: String,
+    pub code: String,
+    /// User that saved the current version.
+    author_id: i64,
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub created_at: DateTime<Utc>,
+    #[allow(dead_code)]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl MathModule {
+    pub fn owner(&self) -> ModuleOwner {
+        match self.scope {
+            ModuleScope::User => ModuleOwner::User(UserId::new(self.owner_id as u64)),
+            ModuleScope::Guild => ModuleOwner::Guild(GuildId::new(self.owner_id as u64)),
+        }
+    }
+
+    pub fn author_id(&self) -> UserId {
+        UserId::new(self.author_id as u64)
+    }
+}
+
+/// Collects modules in order of precedence, e.g. the result of
+/// [`MathModuleRepository::importable_modules`].
+impl FromIterator<MathModule> for Modules {
+    fn from_iter<I: IntoIterator<Item = MathModule>>(iter: I) -> Self {
+        let mut modules = Modules::default();
+
+        for module in iter {
+            let origin = match module.scope {
+                ModuleScope::User => "<user>",
+                ModuleScope::Guild => "<guild>",
+            };
+
+            modules.add(&module.name, origin, &module.code);
+        }
+
+        modules
+    }
+}
+
+pub trait MathModuleRepository {
+    /// Creates the module or replaces the code of an existing one with the same name.
+    async fn save_module(
+        &self,
+        owner: ModuleOwner,
+        author_id: UserId,
+        name: &str,
+        code: &str,
+    ) -> Result<MathModule, Error>;
+
+    async fn get_module(&self, owner: ModuleOwner, name: &str)
+    -> Result<Option<MathModule>, Error>;
+
+    /// Returns the modules of `owner`, sorted by name.
+    async fn list_modules(&self, owner: ModuleOwner) -> Result<Vec<MathModule>, Error>;
+
+    /// Deletes the module, returning whether it existed.
+    async fn delete_module(&self, owner: ModuleOwner, name: &str) -> Result<bool, Error>;
+
+    /// Returns the modules `user_id` can import, their own ones followed by the ones of
+    /// `guild_id`.
+    async fn importable_modules(
+        &self,
+        user_id: UserId,
+        guild_id: Option<GuildId>,
+    ) -> Result<Vec<MathModule>, Error>;
+}
+
+impl MathModuleRepository for Database {
+    async fn save_module(
+        &self,
+        owner: ModuleOwner,
+        author_id: UserId,
+        name: &str,
+        code: &str,
+    ) -> Result<MathModule, Error> {
+        let now = Utc::now();
+
+        let module = sqlx::query_as::<_, MathModule>(
+            "INSERT INTO math_modules
+                 (scope, owner_id, name, code, author_id, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT (scope, owner_id, name) DO UPDATE SET
+                 code = excluded.code,
+                 author_id = excluded.author_id,
+                 updated_at = excluded.updated_at
+             RETURNING *",
+        )
+        .bind(owner.scope())
+        .bind(owner.id())
+        .bind(name)
+        .bind(code)
+        .bind(author_id.get() as i64)
+        .bind(now)
+        .bind(now)
+        .fetch_one(self.pool())
+        .await?;
+
+        Ok(module)
+    }
+
+    async fn get_module(
+        &self,
+        owner: ModuleOwner,
+        name: &str,
+    ) -> Result<Option<MathModule>, Error> {
+        let module = sqlx::query_as::<_, MathModule>(
+            "SELECT * FROM math_modules WHERE scope = ? AND owner_id = ? AND name = ?",
+        )
+        .bind(owner.scope())
+        .bind(owner.id())
+        .bind(name)
+        .fetch_optional(self.pool())
+        .await?;
+
+        Ok(module)
+    }
+
+    async fn list_modules(&self, owner: ModuleOwner) -> Result<Vec<MathModule>, Error> {
+        let modules = sqlx::query_as::<_, MathModule>(
+            "SELECT * FROM math_modules WHERE scope = ? AND owner_id = ? ORDER BY name",
+        )
+        .bind(owner.scope())
+        .bind(owner.id())
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(modules)
+    }
+
+    async fn delete_module(&self, owner: ModuleOwner, name: &str) -> Result<bool, Error> {
+        let result =
+            sqlx::query("DELETE FROM math_modules WHERE scope = ? AND owner_id = ? AND name = ?")
+                .bind(owner.scope())
+                .bind(owner.id())
+                .bind(name)
+                .execute(self.pool())
+                .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn importable_modules(
+        &self,
+        user_id: UserId,
+        guild_id: Option<GuildId>,
+    ) -> Result<Vec<MathModule>, Error> {
+        let modules = sqlx::query_as::<_, MathModule>(
+            "SELECT * FROM math_modules
+             WHERE (scope = ? AND owner_id = ?) OR (scope = ? AND owner_id = ?)
+             ORDER BY scope = ? DESC, name",
+        )
+        .bind(ModuleScope::User)
+        .bind(user_id.get() as i64)
+        .bind(ModuleScope::Guild)
+        .bind(guild_id.map(|guild_id| guild_id.get() as i64))
+        .bind(ModuleScope::User)
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(modules)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn saves_and_replaces_modules() {
+        let database = Database::test().await;
+        let owner = ModuleOwner::Guild(GuildId::new(1));
+
+        let created = database
+            .save_module(owner, UserId::new(2), "team::constants", "let a = 1")
+            .await
+            .unwrap();
+        let replaced = database
+            .save_module(owner, UserId::new(3), "team::constants", "let a = 2")
+            .await
+            .unwrap();
+
+        assert_eq!(created.id, replaced.id);
+        assert_eq!(created.created_at, replaced.created_at);
+        assert_eq!(replaced.owner(), owner);
+        assert_eq!(replaced.author_id(), UserId::new(3));
+
+        let module = database
+            .get_module(owner, "team::constants")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(module.code, "let a = 2");
+
+        // Users and guilds with the same ID have separate modules
+        assert!(
+            database
+                .get_module(ModuleOwner::User(UserId::new(1)), "team::constants")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn lists_and_deletes_modules() {
+        let database = Database::test().await;
+        let owner = ModuleOwner::User(UserId::new(1));
+
+        for name in ["b", "a"] {
+            database
+                .save_module(owner, UserId::new(1), name, "let x = 1")
+                .await
+                .unwrap();
+        }
+
+        let names = |modules: Vec<MathModule>| -> Vec<String> {
+            modules.into_iter().map(|module| module.name).collect()
+        };
+
+        assert_eq!(
+            names(database.list_modules(owner).await.unwrap()),
+            ["a", "b"]
+        );
+
+        assert!(database.delete_module(owner, "a").await.unwrap());
+        assert!(!database.delete_module(owner, "a").await.unwrap());
+        assert_eq!(names(database.list_modules(owner).await.unwrap()), ["b"]);
+    }
+
+    #[tokio::test]
+    async fn lists_importable_modules() {
+        let database = Database::test().await;
+        let user_id = UserId::new(1);
+        let guild_id = GuildId::new(2);
+
+        let modules = [
+            (ModuleOwner::Guild(guild_id), "a"),
+            (ModuleOwner::User(user_id), "b"),
+            (ModuleOwner::User(UserId::new(3)), "c"),
+            (ModuleOwner::Guild(GuildId::new(4)), "d"),
+        ];
+
+        for (owner, name) in modules {
+            database
+                .save_module(owner, user_id, name, "let x = 1")
+                .await
+                .unwrap();
+        }
+
+        let names = |modules: Vec<MathModule>| -> Vec<String> {
+            modules.into_iter().map(|module| module.name).collect()
+        };
+
+        let importable = database
+            .importable_modules(user_id, Some(guild_id))
+            .await
+            .unwrap();
+        assert_eq!(names(importable), ["b", "a"]);
+
+        let importable = database.importable_modules(user_id, None).await.unwrap();
+        assert_eq!(names(importable), ["b"]);
+    }
+}
@@ -1 +1,73 @@
+use std::str::FromStr;
 
+use sqlx::{
+    SqlitePool,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+};
+
+use crate::error::Error;
+
+pub mod conversations;
+pub mod economy;
+pub mod gambling;
+pub mod math_modules;
+pub mod snippets;
+pub mod users;
+
+static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./migrations");
+
+/// Handle to the SQLite database. Cloning is cheap, all clones share the same connection pool.
+#[derive(Clone)]
+pub struct Database {
+    pool: SqlitePool,
+}
+
+impl Database {
+    /// Connects to a database file, creating the file and its parent directories if missing.
+    pub async fn connect(url: &str) -> Result<Self, Error> {
+        let options = SqliteConnectOptions::from_str(url)?
+            .create_if_missing(true)
+            .foreign_keys(true)
+            .journal_mode(SqliteJournalMode::Wal);
+
+        if let Some(parent) = options.get_filename().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let pool = SqlitePoolOptions::new().connect_with(options).await?;
+
+        Ok(Self { pool })
+    }
+
+    /// Creates a fresh, migrated database that only lives as long as the returned handle.
+    #[cfg(test)]
+    pub async fn test() -> Self {
+        let options = SqliteConnectOptions::from_str("sqlite::memory:")
+            .unwrap()
+            .foreign_keys(true);
+
+        // Every connection to `:memory:` opens a separate database, so the pool must keep
+        // exactly one connection alive
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await
+            .unwrap();
+
+        let database = Self { pool };
+        database.migrate().await.unwrap();
+        database
+    }
+
+    /// Applies all migrations that have not been applied yet.
+    pub async fn migrate(&self) -> Result<(), Error> {
+        MIGRATOR.run(&self.pool).await?;
+        Ok(())
+    }
+
+    pub fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+}
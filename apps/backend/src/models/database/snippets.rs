@@ -0,0 +1,154 @@
+use chrono::{DateTime, Utc};
+use serenity::all::UserId;
+use sqlx::types::Json;
+
+use crate::{error::Error, models::api::code::ExecuteFile};
+
+use super::Database;
+
+/// Everything needed to run a piece of code again.
+#[derive(Debug, Clone, Default, PartialEq, Eq, sqlx::FromRow)]
+pub struct SnippetSource {
+    pub language: String,
+    pub version: String,
+    pub code: String,
+    /// Name of the main file, the name of the uploaded file the code was read from.
+    pub name: Option<String>,
+    pub stdin: Option<String>,
+    pub args: Json<Vec<String>>,
+    /// Files sent after the main code, e.g. modules imported by it.
+    pub files: Json<Vec<ExecuteFile>>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Snippet {
+    pub id: i64,
+    user_id: i64,
+    #[sqlx(flatten)]
+    pub source: SnippetSource,
+    #[allow(dead_code)]
+    pub created_at: DateTime<Utc>,
+}
+
+impl Snippet {
+    pub fn user_id(&self) -> UserId {
+        UserId::new(self.user_id as u64)
+    }
+}
+
+pub trait SnippetRepository {
+    /// Stores the source of an execution, returning the ID of the snippet.
+    async fn create_snippet(&self, user_id: UserId, source: &SnippetSource) -> Result<i64, Error>;
+
+    async fn get_snippet(&self, snippet_id: i64) -> Result<Option<Snippet>, Error>;
+
+    /// Deletes the snippets created before `cutoff`, returning how many were deleted. Every run
+    /// stores a snippet including its files, so they are only kept for a while.
+    async fn delete_snippets_before(&self, cutoff: DateTime<Utc>) -> Result<u64, Error>;
+}
+
+impl SnippetRepository for Database {
+    async fn create_snippet(&self, user_id: UserId, source: &SnippetSource) -> Result<i64, Error> {
+        let snippet_id: i64 = sqlx::query_scalar(
+            "INSERT INTO code_snippets
+                 (user_id, language, version, code, name, stdin, args, files, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+             RETURNING id",
+        )
+        .bind(user_id.get() as i64)
+        .bind(&source.language)
+        .bind(&source.version)
+        .bind(&source.code)
+        .bind(&source.name)
+        .bind(&source.stdin)
+        .bind(&source.args)
+        .bind(&source.files)
+        .bind(Utc::now())
+        .fetch_one(self.pool())
+        .await?;
+
+        Ok(snippet_id)
+    }
+
+    async fn get_snippet(&self, snippet_id: i64) -> Result<Option<Snippet>, Error> {
+        let snippet = sqlx::query_as::<_, Snippet>("SELECT * FROM code_snippets WHERE id = ?")
+            .bind(snippet_id)
+            .fetch_optional(self.pool())
+            .await?;
+
+        Ok(snippet)
+    }
+
+    async fn delete_snippets_before(&self, cutoff: DateTime<Utc>) -> Result<u64, Error> {
+        let result = sqlx::query("DELETE FROM code_snippets WHERE created_at < ?")
+            .bind(cutoff)
+            .execute(self.pool())
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn stores_snippets() {
+        let database = Database::test().await;
+
+        let source = SnippetSource {
+            language: String::from("python"),
+            version: String::from("*"),
+            code: String::from("print(input())"),
+            name: Some(String::from("main.py")),
+            stdin: Some(String::from("hello")),
+            args: Json(vec![String::from("-v")]),
+            files: Json(vec![
+                ExecuteFile::new()
+                    .name("helper.py")
+                    .content("NAME = 'helper'"),
+            ]),
+        };
+
+        let snippet_id = database
+            .create_snippet(UserId::new(1), &source)
+            .await
+            .unwrap();
+
+        let snippet = database.get_snippet(snippet_id).await.unwrap().unwrap();
+        assert_eq!(snippet.user_id(), UserId::new(1));
+        assert_eq!(snippet.source, source);
+
+        assert!(
+            database
+                .get_snippet(snippet_id + 1)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn deletes_old_snippets() {
+        let database = Database::test().await;
+
+        let snippet_id = database
+            .create_snippet(UserId::new(1), &SnippetSource::default())
+            .await
+            .unwrap();
+
+        let deleted = database
+            .delete_snippets_before(Utc::now() - chrono::Duration::days(1))
+            .await
+            .unwrap();
+        assert_eq!(deleted, 0);
+
+        let deleted = database
+            .delete_snippets_before(Utc::now() + chrono::Duration::seconds(1))
+            .await
+            .unwrap();
+        assert_eq!(deleted, 1);
+        assert!(database.get_snippet(snippet_id).await.unwrap().is_none());
+    }
+}
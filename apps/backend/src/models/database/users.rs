@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+use serenity::all::UserId;
+
+use crate::error::Error;
+
+use super::Database;
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct User {
+    id: i64,
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub created_at: DateTime<Utc>,
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub last_seen_at: DateTime<Utc>,
+}
+
+impl User {
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn id(&self) -> UserId {
+        UserId::new(self.id as u64)
+    }
+}
+
+pub trait UserRepository {
+    #[cfg_attr(not(test), allow(dead_code))]
+    async fn get_user(&self, user_id: UserId) -> Result<Option<User>, Error>;
+
+    /// Creates the user if it does not exist yet and updates when it was last seen.
+    async fn touch_user(&self, user_id: UserId) -> Result<User, Error>;
+}
+
+impl UserRepository for Database {
+    async fn get_user(&self, user_id: UserId) -> Result<Option<User>, Error> {
+        let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+            .bind(user_id.get() as i64)
+            .fetch_optional(self.pool())
+            .await?;
+
+        Ok(user)
+    }
+
+    async fn touch_user(&self, user_id: UserId) -> Result<User, Error> {
+        let now = Utc::now();
+
+        let user = sqlx::query_as::<_, User>(
+            "INSERT INTO users (id, created_at, last_seen_at) VALUES (?, ?, ?)
+             ON CONFLICT (id) DO UPDATE SET last_seen_at = excluded.last_seen_at
+             RETURNING *",
+        )
+        .bind(user_id.get() as i64)
+        .bind(now)
+        .bind(now)
+        .fetch_one(self.pool())
+        .await?;
+
+        Ok(user)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn missing_user() {
+        let database = Database::test().await;
+
+        assert!(database.get_user(UserId::new(1)).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn touch_creates_and_updates() {
+        let database = Database::test().await;
+        let user_id = UserId::new(778659522054717460);
+
+        let created = database.touch_user(user_id).await.unwrap();
+        let touched = database.touch_user(user_id).await.unwrap();
+
+        assert_eq!(created.id(), user_id);
+        assert_eq!(created.created_at, touched.created_at);
+        assert!(touched.last_seen_at >= created.last_seen_at);
+
+        let user = database.get_user(user_id).await.unwrap().unwrap();
+        assert_eq!(user.last_seen_at, touched.last_seen_at);
+    }
+}
@@ -0,0 +1,410 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::anyhow;
+use serde_json::{Map, Value};
+use serenity::all::{
+    ApplicationId, Command, CommandType, CreateCommand, EntryPointHandlerType, GuildId,
+    InstallationContext, InteractionContext,
+};
+
+use crate::{env::ENV, error::Error, handlers};
+
+/// Fields Discord adds to fetched commands which are not part of the local definition.
+const IGNORED_FIELDS: &[&str] = &[
+    "id",
+    "application_id",
+    "guild_id",
+    "version",
+    "name_localized",
+    "description_localized",
+    "dm_permission",
+];
+
+/// Chat input commands are registered without an explicit type.
+const DEFAULT_COMMAND_TYPE: u64 = 1;
+
+#[derive(Clone, Copy)]
+pub enum Scope {
+    Global,
+    Guild(GuildId),
+}
+
+impl Scope {
+    pub fn new(guild_id: Option<String>) -> Result<Self, Error> {
+        match guild_id {
+            Some(guild_id) => Ok(Self::Guild(GuildId::from_str(&guild_id)?)),
+            None => Ok(Self::Global),
+        }
+    }
+
+    async fn commands(&self, http: &serenity::http::Http) -> Result<Vec<Command>, Error> {
+        let commands = match self {
+            Self::Global => http.get_global_commands().await?,
+            Self::Guild(guild_id) => http.get_guild_commands(*guild_id).await?,
+        };
+
+        Ok(commands)
+    }
+}
+
+impl Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Global => write!(f, "global"),
+            Self::Guild(guild_id) => write!(f, "guild {guild_id}"),
+        }
+    }
+}
+
+pub struct FieldChange {
+    pub field: String,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+pub enum CommandChange {
+    Added(String),
+    Removed(String),
+    Modified(String, Vec<FieldChange>),
+    Unchanged(String),
+}
+
+impl CommandChange {
+    fn is_change(&self) -> bool {
+        !matches!(self, Self::Unchanged(_))
+    }
+}
+
+impl Display for CommandChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Added(name) => write!(f, "+ {name}"),
+            Self::Removed(name) => write!(f, "- {name}"),
+            Self::Unchanged(name) => write!(f, "  {name}"),
+            Self::Modified(name, fields) => {
+                write!(f, "~ {name}")?;
+
+                for change in fields {
+                    let format = |value: &Option<Value>| {
+                        value
+                            .as_ref()
+                            .map(Value::to_string)
+                            .unwrap_or_else(|| String::from("(none)"))
+                    };
+
+                    write!(
+                        f,
+                        "\n    {}: {} -> {}",
+                        change.field,
+                        format(&change.old),
+                        format(&change.new)
+                    )?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Removes values that Discord treats the same as an absent field, so a fetched command and
+/// its local definition serialize identically when nothing changed.
+fn normalize(value: Value) -> Option<Value> {
+    match value {
+        Value::Null | Value::Bool(false) => None,
+        Value::String(s) if s.is_empty() => None,
+        Value::Array(values) => {
+            let values: Vec<_> = values.into_iter().filter_map(normalize).collect();
+            (!values.is_empty()).then_some(Value::Array(values))
+        }
+        Value::Object(map) => {
+            let map: Map<_, _> = map
+                .into_iter()
+                .filter(|(key, _)| !IGNORED_FIELDS.contains(&key.as_str()))
+                .filter_map(|(key, value)| normalize(value).map(|value| (key, value)))
+                .collect();
+            (!map.is_empty()).then_some(Value::Object(map))
+        }
+        value => Some(value),
+    }
+}
+
+fn normalize_command(command: Value) -> Map<String, Value> {
+    let mut map = match normalize(command) {
+        Some(Value::Object(map)) => map,
+        _ => Map::new(),
+    };
+
+    map.entry("type")
+        .or_insert(Value::from(DEFAULT_COMMAND_TYPE));
+    map
+}
+
+fn command_key(command: &Map<String, Value>) -> (String, String) {
+    let kind = command
+        .get("type")
+        .map(Value::to_string)
+        .unwrap_or_default();
+    let name = command
+        .get("name")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+
+    (kind, name)
+}
+
+/// Compares the serialized commands registered on Discord with the local definitions.
+pub fn diff(remote: Vec<Value>, local: Vec<Value>) -> Vec<CommandChange> {
+    let remote: Vec<_> = remote.into_iter().map(normalize_command).collect();
+    let local: Vec<_> = local.into_iter().map(normalize_command).collect();
+
+    let mut changes = vec![];
+
+    for local_command in &local {
+        let key = command_key(local_command);
+        let name = key.1.clone();
+
+        let Some(remote_command) = remote.iter().find(|c| command_key(c) == key) else {
+            changes.push(CommandChange::Added(name));
+            continue;
+        };
+
+        let mut fields: Vec<_> = local_command.keys().chain(remote_command.keys()).collect();
+        fields.sort();
+        fields.dedup();
+
+        let field_changes: Vec<_> = fields
+            .into_iter()
+            .filter(|field| local_command.get(*field) != remote_command.get(*field))
+            .map(|field| FieldChange {
+                field: field.clone(),
+                old: remote_command.get(field).cloned(),
+                new: local_command.get(field).cloned(),
+            })
+            .collect();
+
+        if field_changes.is_empty() {
+            changes.push(CommandChange::Unchanged(name));
+        } else {
+            changes.push(CommandChange::Modified(name, field_changes));
+        }
+    }
+
+    for remote_command in &remote {
+        let key = command_key(remote_command);
+
+        if !local.iter().any(|c| command_key(c) == key) {
+            changes.push(CommandChange::Removed(key.1));
+        }
+    }
+
+    changes
+}
+
+fn http() -> Result<serenity::http::Http, Error> {
+    let http = serenity::http::Http::new(&ENV.discord_token);
+    http.set_application_id(ApplicationId::from_str(&ENV.discord_app_id)?);
+
+    Ok(http)
+}
+
+fn local_commands() -> Vec<CreateCommand> {
+    let entry_point_command = CreateCommand::new("launch")
+        .kind(CommandType::PrimaryEntryPoint)
+        .description("Launch the Liege activity")
+        .handler(EntryPointHandlerType::DiscordLaunchActivity)
+        .integration_types(vec![InstallationContext::Guild, InstallationContext::User])
+        .contexts(vec![
+            InteractionContext::Guild,
+            InteractionContext::BotDm,
+            InteractionContext::PrivateChannel,
+        ]);
+
+    let mut commands = vec![entry_point_command];
+    commands.extend(handlers::commands::commands());
+    commands
+}
+
+pub async fn register(scope: Scope, dry_run: bool) -> Result<(), Error> {
+    let http = http()?;
+
+    let remote = scope
+        .commands(&http)
+        .await?
+        .iter()
+        .map(serde_json::to_value)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let commands = local_commands();
+    let local = commands
+        .iter()
+        .map(serde_json::to_value)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let changes = diff(remote, local);
+    let change_count = changes.iter().filter(|c| c.is_change()).count();
+
+    println!("Diff for {scope} commands:");
+    for change in &changes {
+        println!("{change}");
+    }
+
+    if change_count == 0 {
+        println!("Commands are up to date, nothing to register");
+        return Ok(());
+    }
+
+    if dry_run {
+        println!("{change_count} command(s) would change, skipping registration (dry run)");
+        return Ok(());
+    }
+
+    if let Scope::Global = scope {
+        // The entry point command can not be overwritten in bulk, so it is recreated
+        let entry_point_id = http
+            .get_global_commands()
+            .await?
+            .iter()
+            .find(|c| c.kind == CommandType::PrimaryEntryPoint)
+            .map(|c| c.id);
+
+        if let Some(entry_point_id) = entry_point_id {
+            Command::delete_global_command(&http, entry_point_id).await?;
+        }
+    }
+
+    println!("Registering {} {scope} commands", commands.len());
+
+    match scope {
+        Scope::Global => {
+            Command::set_global_commands(&http, commands).await?;
+        }
+        Scope::Guild(guild_id) => {
+            guild_id.set_commands(&http, commands).await?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn list(scope: Scope) -> Result<(), Error> {
+    let http = http()?;
+    let commands = scope.commands(&http).await?;
+
+    println!("{} {scope} commands:", commands.len());
+    for command in commands {
+        println!(
+            "{} {} ({:?}) {}",
+            command.id, command.name, command.kind, command.description
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn delete(scope: Scope, name: &str) -> Result<(), Error> {
+    let http = http()?;
+
+    let command = scope
+        .commands(&http)
+        .await?
+        .into_iter()
+        .find(|c| c.name == name)
+        .ok_or_else(|| anyhow!("No {scope} command with name '{name}'"))?;
+
+    match scope {
+        Scope::Global => http.delete_global_command(command.id).await?,
+        Scope::Guild(guild_id) => http.delete_guild_command(guild_id, command.id).await?,
+    }
+
+    println!("Deleted {scope} command {} ({})", command.name, command.id);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn remote_command(name: &str, description: &str) -> Value {
+        json!({
+            "id": "1",
+            "application_id": "2",
+            "version": "3",
+            "type": 1,
+            "name": name,
+            "description": description,
+            "options": [],
+            "nsfw": false,
+            "default_member_permissions": null,
+            "integration_types": [0, 1],
+        })
+    }
+
+    fn local_command(name: &str, description: &str) -> Value {
+        json!({
+            "name": name,
+            "name_localizations": {},
+            "description": description,
+            "description_localizations": {},
+            "options": [],
+            "nsfw": false,
+            "integration_types": [0, 1],
+        })
+    }
+
+    #[test]
+    fn unchanged() {
+        let changes = diff(
+            vec![remote_command("math", "Math")],
+            vec![local_command("math", "Math")],
+        );
+
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(&changes[0], CommandChange::Unchanged(name) if name == "math"));
+    }
+
+    #[test]
+    fn added_and_removed() {
+        let changes = diff(
+            vec![remote_command("old", "Old")],
+            vec![local_command("new", "New")],
+        );
+
+        assert_eq!(changes.len(), 2);
+        assert!(matches!(&changes[0], CommandChange::Added(name) if name == "new"));
+        assert!(matches!(&changes[1], CommandChange::Removed(name) if name == "old"));
+    }
+
+    #[test]
+    fn modified() {
+        let changes = diff(
+            vec![remote_command("math", "Math")],
+            vec![local_command("math", "Evaluate math")],
+        );
+
+        let CommandChange::Modified(name, fields) = &changes[0] else {
+            panic!("expected modified command");
+        };
+
+        assert_eq!(name, "math");
+        assert_eq!(fields.len(), 1);
+        assert_eq!(fields[0].field, "description");
+        assert_eq!(fields[0].old, Some(json!("Math")));
+        assert_eq!(fields[0].new, Some(json!("Evaluate math")));
+    }
+
+    #[test]
+    fn local_definitions_serialize() {
+        let local = local_commands()
+            .iter()
+            .map(|c| serde_json::to_value(c).unwrap())
+            .collect::<Vec<_>>();
+
+        let changes = diff(local.clone(), local);
+        assert!(changes.iter().all(|c| !c.is_change()));
+    }
+}
@@ -0,0 +1,120 @@
+use std::future::ready;
+
+use futures::{Stream, StreamExt, TryStreamExt};
+use reqwest_eventsource::{Event, RequestBuilderExt, retry};
+
+use crate::{
+    error::Error,
+    models::api::ai::{
+        GenerateTextMessage, GenerateTextRequest, GenerateTextResponse, GenerateTextStreamResponse,
+    },
+};
+
+use super::{Completion, TextGenerator, estimate_tokens};
+
+/// Generates text with a model of the AI API.
+pub struct AiClient {
+    http: reqwest::Client,
+    base_url: String,
+    token: String,
+    model: String,
+}
+
+impl AiClient {
+    pub fn new(
+        http: reqwest::Client,
+        base_url: impl Into<String>,
+        token: impl Into<String>,
+        model: impl Into<String>,
+    ) -> Self {
+        Self {
+            http,
+            base_url: base_url.into(),
+            token: token.into(),
+            model: model.into(),
+        }
+    }
+
+    /// Streams the text generated by `model`, which can differ from the model of the client.
+    pub fn stream_with_model(
+        &self,
+        model: &str,
+        messages: Vec<GenerateTextMessage>,
+    ) -> Result<impl Stream<Item = Result<String, Error>> + use<>, Error> {
+        let mut event_source = self
+            .http
+            .post(format!("{}/v2/generate/text", self.base_url))
+            .header("Authorization", &self.token)
+            .json(
+                &GenerateTextRequest::new()
+                    .model(model)
+                    .messages(messages)
+                    .stream(true),
+            )
+            .eventsource()?;
+
+        event_source.set_retry_policy(Box::new(retry::Never));
+
+        let chunks = event_source
+            .take_while(|event| {
+                ready(
+                    !matches!(
+                        event,
+                        Ok(Event::Message(message)) if message.data == "[DONE]"
+                    ) && !matches!(event, Err(reqwest_eventsource::Error::StreamEnded)),
+                )
+            })
+            .map_err(Error::from)
+            .try_filter_map(|event| async move {
+                match event {
+                    Event::Open => Ok(None),
+                    Event::Message(message) => {
+                        let data =
+                            serde_json::from_str::<GenerateTextStreamResponse>(&message.data)?;
+                        Ok(Some(data.response))
+                    }
+                }
+            });
+
+        Ok(chunks)
+    }
+}
+
+impl TextGenerator for AiClient {
+    async fn generate(&self, messages: Vec<GenerateTextMessage>) -> Result<Completion, Error> {
+        let response = self
+            .http
+            .post(format!("{}/v3/generate/text", self.base_url))
+            .header("Authorization", &self.token)
+            .json(
+                &GenerateTextRequest::new()
+                    .model(&self.model)
+                    .messages(messages),
+            )
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<GenerateTextResponse>()
+            .await?;
+
+        let content = response
+            .choices
+            .first()
+            .and_then(|choice| choice.message.content.clone())
+            .unwrap_or("[empty response]".into());
+
+        let tokens = response
+            .usage
+            .map(|usage| usage.total_tokens as i64)
+            .unwrap_or_else(|| estimate_tokens(&content));
+
+        Ok(Completion { content, tokens })
+    }
+
+    fn stream(
+        &self,
+        messages: Vec<GenerateTextMessage>,
+    ) -> Result<impl Stream<Item = Result<String, Error>>, Error> {
+        self.stream_with_model(&self.model, messages)
+    }
+}
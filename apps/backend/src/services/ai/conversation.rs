@@ -0,0 +1,327 @@
+use std::pin::pin;
+
+use futures::StreamExt;
+
+use crate::{
+    error::Error,
+    models::{
+        api::ai::{GenerateTextMessage, GenerateTextMessageRole},
+        database::{
+            Database,
+            conversations::{
+                Conversation, ConversationMessage, ConversationRepository, MessageRole,
+            },
+        },
+    },
+};
+
+use super::{Completion, SYSTEM_PROMPT, TextGenerator, estimate_tokens};
+
+/// Tokens a conversation may use in total, counting every request including summaries.
+pub const TOKEN_BUDGET: i64 = 50_000;
+
+/// Number of messages in the history above which older messages are summarized.
+const MAX_HISTORY: usize = 16;
+
+/// Number of recent messages kept as they are when summarizing, even so that the history
+/// keeps starting with a prompt.
+const KEEP_RECENT: usize = 4;
+
+const SUMMARY_PROMPT: &str = "Summarize the conversation so far in at most 150 words for \
+yourself, so it can be continued without the full history. Keep names, numbers and open \
+questions, and leave out pleasantries.";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConversationError {
+    #[error(
+        "This conversation used up its budget of {TOKEN_BUDGET} tokens, start a new one with `/ai text`"
+    )]
+    BudgetExceeded,
+
+    #[error(transparent)]
+    Other(#[from] Error),
+}
+
+/// Generates the reply to `prompt` with the history of `conversation`, and adds both to the
+/// history. Once the history grows too long, its older messages are replaced by a summary.
+///
+/// The reply is streamed, `on_text` is called with the text generated so far whenever a chunk
+/// of it arrives. Its errors are logged without stopping the reply.
+pub async fn reply<F>(
+    database: &Database,
+    generator: &impl TextGenerator,
+    conversation: &Conversation,
+    prompt: &str,
+    mut on_text: impl FnMut(String) -> F,
+) -> Result<Completion, ConversationError>
+where
+    F: Future<Output = Result<(), Error>>,
+{
+    if conversation.tokens_used >= TOKEN_BUDGET {
+        return Err(ConversationError::BudgetExceeded);
+    }
+
+    let history = database.conversation_messages(conversation.id).await?;
+
+    let mut messages = context(conversation.summary.as_deref(), &history);
+    messages.push(GenerateTextMessage::new(
+        GenerateTextMessageRole::User,
+        prompt,
+    ));
+
+    // Streamed responses do not report their usage, so the tokens are estimated
+    let prompt_tokens: i64 = messages
+        .iter()
+        .map(|message| estimate_tokens(message.content()))
+        .sum();
+
+    let mut chunks = pin!(generator.stream(messages)?);
+    let mut content = String::new();
+
+    while let Some(chunk) = chunks.next().await {
+        content.push_str(&chunk?);
+
+        // Progress is only shown while generating, a failed update must not lose the reply
+        if let Err(error) = on_text(content.clone()).await {
+            tracing::warn!(%error, conversation_id = conversation.id, "failed to show reply progress");
+        }
+    }
+
+    if content.is_empty() {
+        content = String::from("[empty response]");
+    }
+
+    let completion = Completion {
+        tokens: prompt_tokens + estimate_tokens(&content),
+        content,
+    };
+
+    database
+        .add_exchange(
+            conversation.id,
+            prompt,
+            &completion.content,
+            completion.tokens,
+        )
+        .await?;
+
+    // The reply was already stored, so a failed summary is retried with the next prompt
+    if history.len() + 2 > MAX_HISTORY
+        && let Err(error) = summarize(database, generator, conversation).await
+    {
+        tracing::warn!(%error, conversation_id = conversation.id, "failed to summarize conversation");
+    }
+
+    Ok(completion)
+}
+
+/// Messages sent ahead of a prompt, the system prompt followed by the summary and history.
+fn context(summary: Option<&str>, history: &[ConversationMessage]) -> Vec<GenerateTextMessage> {
+    let mut messages = vec![GenerateTextMessage::new(
+        GenerateTextMessageRole::System,
+        SYSTEM_PROMPT,
+    )];
+
+    if let Some(summary) = summary {
+        messages.push(GenerateTextMessage::new(
+            GenerateTextMessageRole::System,
+            &format!("Summary of the earlier conversation:\n{summary}"),
+        ));
+    }
+
+    messages.extend(history.iter().map(|message| {
+        let role = match message.role {
+            MessageRole::User => GenerateTextMessageRole::User,
+            MessageRole::Assistant => GenerateTextMessageRole::Assistant,
+        };
+
+        GenerateTextMessage::new(role, &message.content)
+    }));
+
+    messages
+}
+
+/// Replaces all but the most recent messages of `conversation` with a summary, which includes
+/// the previous summary.
+async fn summarize(
+    database: &Database,
+    generator: &impl TextGenerator,
+    conversation: &Conversation,
+) -> Result<(), Error> {
+    let history = database.conversation_messages(conversation.id).await?;
+    let summarized = &history[..history.len().saturating_sub(KEEP_RECENT)];
+
+    let Some(last) = summarized.last() else {
+        return Ok(());
+    };
+
+    let mut messages = context(conversation.summary.as_deref(), summarized);
+    messages.push(GenerateTextMessage::new(
+        GenerateTextMessageRole::User,
+        SUMMARY_PROMPT,
+    ));
+
+    let completion = generator.generate(messages).await?;
+
+    database
+        .summarize_conversation(
+            conversation.id,
+            &completion.content,
+            last.id,
+            completion.tokens,
+        )
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::all::{ChannelId, UserId};
+
+    use super::*;
+    use crate::services::ai::fake::FakeGenerator;
+
+    async fn conversation(database: &Database) -> Conversation {
+        database
+            .create_conversation(ChannelId::new(1), UserId::new(2))
+            .await
+            .unwrap()
+    }
+
+    /// Reloads the conversation, as the handlers do for every prompt.
+    async fn reload(database: &Database, conversation: &Conversation) -> Conversation {
+        database
+            .get_conversation(conversation.id)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    async fn ignore(_text: String) -> Result<(), Error> {
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sends_history() {
+        let database = Database::test().await;
+        let generator = FakeGenerator::with_tokens(10);
+        let conversation = conversation(&database).await;
+
+        let mut updates = Vec::new();
+        let first = reply(&database, &generator, &conversation, "hi", |text| {
+            updates.push(text);
+            ignore(String::new())
+        })
+        .await
+        .unwrap();
+        assert_eq!(first.content, "reply 1");
+        assert_eq!(updates, ["reply ", "reply 1"]);
+
+        let conversation = reload(&database, &conversation).await;
+        assert_eq!(conversation.tokens_used, first.tokens);
+
+        reply(&database, &generator, &conversation, "again", ignore)
+            .await
+            .unwrap();
+
+        assert!(reload(&database, &conversation).await.tokens_used > first.tokens);
+
+        let requests = generator.requests.lock().unwrap();
+        let request = requests[1].as_array().unwrap();
+        let contents: Vec<_> = request[1..]
+            .iter()
+            .map(|message| message["content"].as_str().unwrap())
+            .collect();
+
+        assert_eq!(request[0]["role"], "system");
+        assert_eq!(contents, ["hi", "reply 1", "again"]);
+        assert_eq!(request[2]["role"], "assistant");
+    }
+
+    #[tokio::test]
+    async fn ignores_failed_progress() {
+        let database = Database::test().await;
+        let generator = FakeGenerator::default();
+        let conversation = conversation(&database).await;
+
+        let completion = reply(&database, &generator, &conversation, "hi", |_| async {
+            Err(anyhow::anyhow!("edit failed"))
+        })
+        .await
+        .unwrap();
+        assert_eq!(completion.content, "reply 1");
+
+        let history = database
+            .conversation_messages(conversation.id)
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn summarizes_long_history() {
+        let database = Database::test().await;
+        let generator = FakeGenerator::with_tokens(1);
+        let mut conversation = conversation(&database).await;
+
+        for i in 0..MAX_HISTORY / 2 + 1 {
+            reply(
+                &database,
+                &generator,
+                &conversation,
+                &format!("prompt {i}"),
+                ignore,
+            )
+            .await
+            .unwrap();
+            conversation = reload(&database, &conversation).await;
+        }
+
+        // The last prompt is followed by a request for the summary
+        let requests = generator.requests.lock().unwrap().len();
+        assert_eq!(requests, MAX_HISTORY / 2 + 2);
+        assert_eq!(
+            conversation.summary.as_deref(),
+            Some(format!("reply {requests}").as_str())
+        );
+
+        let history = database
+            .conversation_messages(conversation.id)
+            .await
+            .unwrap();
+        assert_eq!(history.len(), KEEP_RECENT);
+        assert_eq!(history[0].role, MessageRole::User);
+
+        reply(&database, &generator, &conversation, "more", ignore)
+            .await
+            .unwrap();
+
+        let requests = generator.requests.lock().unwrap();
+        let request = requests.last().unwrap().as_array().unwrap();
+        assert_eq!(request[1]["role"], "system");
+        assert!(
+            request[1]["content"]
+                .as_str()
+                .unwrap()
+                .ends_with(&format!("reply {}", requests.len() - 1))
+        );
+        assert_eq!(request.len(), 2 + KEEP_RECENT + 1);
+    }
+
+    #[tokio::test]
+    async fn enforces_budget() {
+        let database = Database::test().await;
+        let generator = FakeGenerator::default();
+        let conversation = conversation(&database).await;
+
+        database
+            .add_exchange(conversation.id, "hi", "hello", TOKEN_BUDGET)
+            .await
+            .unwrap();
+
+        let conversation = reload(&database, &conversation).await;
+        let result = reply(&database, &generator, &conversation, "again", ignore).await;
+
+        assert!(matches!(result, Err(ConversationError::BudgetExceeded)));
+        assert!(generator.requests.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,50 @@
+use std::sync::Mutex;
+
+use futures::{Stream, stream};
+
+use crate::{error::Error, models::api::ai::GenerateTextMessage};
+
+use super::{Completion, TextGenerator};
+
+/// In-process generator answering with numbered replies and recording every request it
+/// receives.
+#[derive(Default)]
+pub struct FakeGenerator {
+    /// Tokens reported for every completion that is not streamed.
+    pub tokens: i64,
+    pub requests: Mutex<Vec<serde_json::Value>>,
+}
+
+impl FakeGenerator {
+    pub fn with_tokens(tokens: i64) -> Self {
+        Self {
+            tokens,
+            ..Default::default()
+        }
+    }
+}
+
+impl TextGenerator for FakeGenerator {
+    async fn generate(&self, messages: Vec<GenerateTextMessage>) -> Result<Completion, Error> {
+        let mut requests = self.requests.lock().unwrap();
+        requests.push(serde_json::to_value(&messages)?);
+
+        Ok(Completion {
+            content: format!("reply {}", requests.len()),
+            tokens: self.tokens,
+        })
+    }
+
+    /// Streams the same reply as [`FakeGenerator::generate`], split after its first word.
+    fn stream(
+        &self,
+        messages: Vec<GenerateTextMessage>,
+    ) -> Result<impl Stream<Item = Result<String, Error>>, Error> {
+        let mut requests = self.requests.lock().unwrap();
+        requests.push(serde_json::to_value(&messages)?);
+
+        let chunks = [String::from("reply "), requests.len().to_string()];
+
+        Ok(stream::iter(chunks.map(Ok)))
+    }
+}
@@ -0,0 +1,110 @@
+//! Text generation for `/ai text`, with conversations that are continued by later prompts.
+
+use futures::Stream;
+use regex::Regex;
+use reqwest::Url;
+
+use crate::{error::Error, models::api::ai::GenerateTextMessage};
+
+mod client;
+mod conversation;
+#[cfg(test)]
+mod fake;
+mod throttle;
+
+pub use client::AiClient;
+pub use conversation::{ConversationError, TOKEN_BUDGET, reply};
+pub use throttle::EditThrottle;
+
+pub const SYSTEM_PROMPT: &str = "You are Liege, a friendly and helpful chatbot designed to assist users with various inquiries. Your responses should be:
+
+1. **Concise & Relevant** - Provide clear, direct answers without unnecessary elaboration.  
+2. **Under 200 words** - Ensure every response stays within this limit. Trim excess details if needed.  
+3. **Engaging & Polite** - Maintain a friendly and professional tone.
+4. **Accurate & Informative** - Base your answers on verified information, avoiding speculation.  
+
+If a user request requires a longer response, summarize the key points.";
+
+/// Generated text together with the number of tokens used for the request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub content: String,
+    pub tokens: i64,
+}
+
+pub trait TextGenerator {
+    async fn generate(&self, messages: Vec<GenerateTextMessage>) -> Result<Completion, Error>;
+
+    /// Generates text like [`TextGenerator::generate`], yielding the chunks of text as they are
+    /// generated. Streamed responses do not report the tokens they used.
+    fn stream(
+        &self,
+        messages: Vec<GenerateTextMessage>,
+    ) -> Result<impl Stream<Item = Result<String, Error>>, Error>;
+}
+
+/// Rough number of tokens in `text`, for responses that do not report their usage.
+pub fn estimate_tokens(text: &str) -> i64 {
+    text.chars().count().div_ceil(4) as i64
+}
+
+/// Replaces the footnotes of a generated response with a line of references to the hosts of
+/// the cited links, and cuts it to the length of a Discord message.
+pub fn format_response(raw_response: &str) -> Result<String, Error> {
+    let footnote_link_regex = Regex::new(r"\[[0-9]+\] \[.+\]\((.+)\)")?;
+    let link_matches: Vec<&str> = footnote_link_regex
+        .captures_iter(raw_response)
+        .filter_map(|caps| caps.get(1).map(|m| m.as_str()))
+        .collect();
+
+    let footnote_list_regex = Regex::new(r"\n\n\n> \[0\][\S\s]*$")?;
+    let inline_footnote_regex = Regex::new(r"\[[0-9]+\]")?;
+
+    let replaced_response = footnote_list_regex.replace_all(raw_response, "");
+    let replaced_response = inline_footnote_regex
+        .replace_all(&replaced_response, "")
+        .to_string();
+    let mut response = replaced_response.to_string();
+
+    let sources = link_matches
+        .into_iter()
+        .filter_map(|url_str| {
+            Url::parse(url_str)
+                .ok()
+                .and_then(|url| url.host_str().map(|host| format!("[{host}](<{url}>)")))
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    if !sources.is_empty() {
+        response.push_str(&format!("\n-# > References: {sources}"));
+    }
+
+    if let Some((index, _)) = response.char_indices().nth(2000) {
+        response.truncate(index);
+    }
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_references() {
+        let raw = "Rust is a language[0].\n\n\n> [0] [Rust](https://www.rust-lang.org/learn)";
+
+        assert_eq!(
+            format_response(raw).unwrap(),
+            "Rust is a language.\n-# > References: [www.rust-lang.org](<https://www.rust-lang.org/learn>)"
+        );
+    }
+
+    #[test]
+    fn cuts_long_responses() {
+        let response = format_response(&"ä".repeat(3000)).unwrap();
+
+        assert_eq!(response.chars().count(), 2000);
+    }
+}
@@ -0,0 +1,90 @@
+use std::time::{Duration, Instant};
+
+use super::estimate_tokens;
+
+/// Minimum time between edits, Discord allows 5 edits of a message per 5 seconds.
+const MIN_INTERVAL: Duration = Duration::from_millis(1200);
+
+/// Time after which a response is edited even if few tokens were added.
+const MAX_INTERVAL: Duration = Duration::from_millis(3000);
+
+/// Tokens added to a response after which it is edited.
+const EDIT_TOKENS: i64 = 40;
+
+/// Decides when a message showing a response that is still being generated is edited, every
+/// [`EDIT_TOKENS`] tokens or [`MAX_INTERVAL`], whichever comes first.
+pub struct EditThrottle {
+    last_edit: Instant,
+    last_tokens: i64,
+}
+
+impl Default for EditThrottle {
+    fn default() -> Self {
+        Self {
+            last_edit: Instant::now(),
+            last_tokens: 0,
+        }
+    }
+}
+
+impl EditThrottle {
+    /// Whether the message should be edited to show `text`, the response generated so far.
+    pub fn ready(&mut self, text: &str) -> bool {
+        self.ready_at(Instant::now(), text)
+    }
+
+    fn ready_at(&mut self, now: Instant, text: &str) -> bool {
+        let elapsed = now.duration_since(self.last_edit);
+        let tokens = estimate_tokens(text);
+        let added = tokens - self.last_tokens;
+
+        if elapsed < MIN_INTERVAL || added <= 0 {
+            return false;
+        }
+
+        if added < EDIT_TOKENS && elapsed < MAX_INTERVAL {
+            return false;
+        }
+
+        self.last_edit = now;
+        self.last_tokens = tokens;
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_after_enough_tokens() {
+        let start = Instant::now();
+        let mut throttle = EditThrottle {
+            last_edit: start,
+            last_tokens: 0,
+        };
+        let text = "a".repeat(EDIT_TOKENS as usize * 4);
+
+        // Edits are rate limited even if the response grows quickly
+        assert!(!throttle.ready_at(start + MIN_INTERVAL / 2, &text));
+        assert!(throttle.ready_at(start + MIN_INTERVAL, &text));
+
+        // Nothing was added since the last edit
+        assert!(!throttle.ready_at(start + MAX_INTERVAL * 2, &text));
+    }
+
+    #[test]
+    fn edits_after_max_interval() {
+        let start = Instant::now();
+        let mut throttle = EditThrottle {
+            last_edit: start,
+            last_tokens: 0,
+        };
+
+        assert!(!throttle.ready_at(start + MIN_INTERVAL, "short"));
+        assert!(throttle.ready_at(start + MAX_INTERVAL, "short"));
+        assert!(!throttle.ready_at(start + MAX_INTERVAL + MIN_INTERVAL, "short and"));
+        assert!(throttle.ready_at(start + MAX_INTERVAL * 2, "short and"));
+    }
+}
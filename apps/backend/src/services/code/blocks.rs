@@ -0,0 +1,93 @@
+/// A fenced code block, e.g. from a Discord message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeBlock {
+    /// Tag after the opening fence, if any.
+    pub language: Option<String>,
+    pub code: String,
+}
+
+/// Extracts all fenced code blocks of `content` in order. Unterminated blocks are ignored.
+pub fn extract_code_blocks(content: &str) -> Vec<CodeBlock> {
+    let mut blocks = vec![];
+    let mut rest = content;
+
+    while let Some(start) = rest.find("```") {
+        let after_fence = &rest[start + 3..];
+
+        let Some(end) = after_fence.find("```") else {
+            break;
+        };
+
+        let inner = &after_fence[..end];
+        rest = &after_fence[end + 3..];
+
+        // The language tag is only recognized if the code starts on a new line, otherwise
+        // ```print(1)``` would be read as the language `print(1)`
+        let (language, code) = match inner.split_once('\n') {
+            Some((tag, code)) if !tag.trim().contains(char::is_whitespace) => {
+                let tag = tag.trim();
+                ((!tag.is_empty()).then(|| tag.to_lowercase()), code)
+            }
+            _ => (None, inner),
+        };
+
+        if code.trim().is_empty() {
+            continue;
+        }
+
+        blocks.push(CodeBlock {
+            language,
+            code: code.trim_end_matches('\n').to_string(),
+        });
+    }
+
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(language: Option<&str>, code: &str) -> CodeBlock {
+        CodeBlock {
+            language: language.map(String::from),
+            code: code.to_string(),
+        }
+    }
+
+    #[test]
+    fn extracts_tagged_blocks() {
+        let content = "Look at this:\n```py\nprint(1)\n```\nand\n```Rust\nfn main() {}\n```";
+
+        assert_eq!(
+            extract_code_blocks(content),
+            [
+                block(Some("py"), "print(1)"),
+                block(Some("rust"), "fn main() {}")
+            ]
+        );
+    }
+
+    #[test]
+    fn extracts_untagged_blocks() {
+        assert_eq!(
+            extract_code_blocks("```print(1)```"),
+            [block(None, "print(1)")]
+        );
+        assert_eq!(
+            extract_code_blocks("```\necho hi\n```"),
+            [block(None, "echo hi")]
+        );
+        assert_eq!(
+            extract_code_blocks("```let a = 1;\nlet b = 2;```"),
+            [block(None, "let a = 1;\nlet b = 2;")]
+        );
+    }
+
+    #[test]
+    fn ignores_empty_and_unterminated_blocks() {
+        assert!(extract_code_blocks("```js\n```").is_empty());
+        assert!(extract_code_blocks("```js\nconsole.log(1)").is_empty());
+        assert!(extract_code_blocks("no code here").is_empty());
+    }
+}
@@ -0,0 +1,186 @@
+use std::sync::RwLock;
+
+use chrono::{DateTime, Utc};
+
+use crate::{error::Error, models::api::code::Language};
+
+use super::CodeExecutor;
+
+/// Cached list of the runtimes offered by the execution service.
+#[derive(Default)]
+pub struct LanguageCatalogue {
+    languages: RwLock<Vec<Language>>,
+    refreshed_at: RwLock<Option<DateTime<Utc>>>,
+}
+
+impl LanguageCatalogue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the cached runtimes with the current list of the execution service.
+    pub async fn refresh(&self, executor: &impl CodeExecutor) -> Result<(), Error> {
+        let languages = executor.languages().await?;
+
+        tracing::debug!(count = languages.len(), "refreshed language catalogue");
+
+        *self.languages.write().unwrap() = languages;
+        *self.refreshed_at.write().unwrap() = Some(Utc::now());
+
+        Ok(())
+    }
+
+    /// Refreshes the catalogue if it has never been loaded, e.g. because the execution service
+    /// was unreachable at startup.
+    pub async fn ensure_loaded(&self, executor: &impl CodeExecutor) -> Result<(), Error> {
+        if self.refreshed_at().is_none() {
+            self.refresh(executor).await?;
+        }
+
+        Ok(())
+    }
+
+    pub fn refreshed_at(&self) -> Option<DateTime<Utc>> {
+        *self.refreshed_at.read().unwrap()
+    }
+
+    pub fn languages(&self) -> Vec<Language> {
+        self.languages.read().unwrap().clone()
+    }
+
+    /// Returns the runtimes whose name or aliases contain `query`, with the newest version of
+    /// each language only.
+    pub fn search(&self, query: &str) -> Vec<Language> {
+        let query = query.to_lowercase();
+        let mut results: Vec<Language> = vec![];
+
+        for language in self.languages.read().unwrap().iter() {
+            let matches = language.language.contains(&query)
+                || language.aliases.iter().any(|alias| alias.contains(&query));
+
+            if !matches {
+                continue;
+            }
+
+            match results
+                .iter_mut()
+                .find(|result| result.language == language.language)
+            {
+                Some(result) => {
+                    if compare_versions(&language.version, &result.version).is_gt() {
+                        *result = language.clone();
+                    }
+                }
+                None => results.push(language.clone()),
+            }
+        }
+
+        results
+    }
+
+    /// Finds the runtime with the given name or alias, case insensitively.
+    pub fn resolve(&self, name: &str) -> Option<Language> {
+        let name = name.to_lowercase();
+
+        self.search(&name)
+            .into_iter()
+            .find(|language| language.language == name || language.aliases.contains(&name))
+    }
+
+    /// Returns all versions of the language with the given name or alias, newest first.
+    pub fn versions(&self, name: &str) -> Vec<String> {
+        let Some(language) = self.resolve(name) else {
+            return vec![];
+        };
+
+        let mut versions: Vec<_> = self
+            .languages
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|runtime| runtime.language == language.language)
+            .map(|runtime| runtime.version.clone())
+            .collect();
+
+        versions.sort_by(|a, b| compare_versions(b, a));
+        versions.dedup();
+        versions
+    }
+}
+
+/// Compares dotted version strings numerically, so that `1.10.0` is newer than `1.9.0`.
+fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let parse = |version: &str| -> Vec<u64> {
+        version
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    };
+
+    parse(a).cmp(&parse(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::services::code::FakeExecutor;
+
+    use super::*;
+
+    fn language(name: &str, version: &str, aliases: &[&str]) -> Language {
+        Language {
+            language: name.to_string(),
+            version: version.to_string(),
+            aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+            runtime: None,
+        }
+    }
+
+    async fn catalogue() -> LanguageCatalogue {
+        let executor = FakeExecutor {
+            languages: vec![
+                language("python", "3.9.4", &["py"]),
+                language("python", "3.12.0", &["py", "py3"]),
+                language("javascript", "18.15.0", &["js", "node"]),
+                language("rust", "1.9.0", &["rs"]),
+                language("rust", "1.10.0", &["rs"]),
+            ],
+            ..Default::default()
+        };
+
+        let catalogue = LanguageCatalogue::new();
+        assert!(catalogue.refreshed_at().is_none());
+
+        catalogue.ensure_loaded(&executor).await.unwrap();
+        assert!(catalogue.refreshed_at().is_some());
+
+        catalogue
+    }
+
+    #[tokio::test]
+    async fn searches_newest_versions() {
+        let catalogue = catalogue().await;
+
+        let results = catalogue.search("py");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].version, "3.12.0");
+
+        assert_eq!(catalogue.search("").len(), 3);
+        assert_eq!(catalogue.search("node")[0].language, "javascript");
+    }
+
+    #[tokio::test]
+    async fn resolves_aliases() {
+        let catalogue = catalogue().await;
+
+        assert_eq!(catalogue.resolve("JS").unwrap().language, "javascript");
+        assert!(catalogue.resolve("java").is_none());
+    }
+
+    #[tokio::test]
+    async fn lists_versions() {
+        let catalogue = catalogue().await;
+
+        assert_eq!(catalogue.versions("rs"), ["1.10.0", "1.9.0"]);
+        assert!(catalogue.versions("go").is_empty());
+    }
+}
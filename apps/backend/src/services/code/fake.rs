@@ -0,0 +1,54 @@
+use std::sync::Mutex;
+
+use crate::{
+    error::Error,
+    models::api::code::{ExecuteRequest, ExecuteResponse, ExecuteStage, LanguagesResponse},
+};
+
+use super::CodeExecutor;
+
+/// In-process executor returning a canned response and recording every request it receives.
+#[derive(Default)]
+pub struct FakeExecutor {
+    pub response: ExecuteResponse,
+    pub languages: LanguagesResponse,
+    pub requests: Mutex<Vec<serde_json::Value>>,
+}
+
+impl FakeExecutor {
+    pub fn with_output(output: &str) -> Self {
+        Self::with_response(ExecuteResponse {
+            language: String::from("python"),
+            version: String::from("3.12.0"),
+            run: ExecuteStage {
+                stdout: output.to_string(),
+                output: output.to_string(),
+                code: Some(0),
+                ..Default::default()
+            },
+            compile: None,
+        })
+    }
+
+    pub fn with_response(response: ExecuteResponse) -> Self {
+        Self {
+            response,
+            ..Default::default()
+        }
+    }
+}
+
+impl CodeExecutor for FakeExecutor {
+    async fn execute(&self, request: &ExecuteRequest) -> Result<ExecuteResponse, Error> {
+        self.requests
+            .lock()
+            .unwrap()
+            .push(serde_json::to_value(request)?);
+
+        Ok(self.response.clone())
+    }
+
+    async fn languages(&self) -> Result<LanguagesResponse, Error> {
+        Ok(self.languages.clone())
+    }
+}
@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::api::code::ExecuteRequest;
+
+/// Upper bounds for the resources a single execution may use. Timeouts are in milliseconds and
+/// memory limits in bytes, as expected by the execution service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LimitPolicy {
+    pub run_timeout: usize,
+    pub compile_timeout: usize,
+    pub run_memory_limit: usize,
+    pub compile_memory_limit: usize,
+}
+
+impl LimitPolicy {
+    /// Limits for executions started from Discord interactions.
+    pub const DISCORD: Self = Self {
+        run_timeout: 3_000,
+        compile_timeout: 10_000,
+        run_memory_limit: 128 * 1024 * 1024,
+        compile_memory_limit: 256 * 1024 * 1024,
+    };
+
+    /// Limits for executions started through the HTTP API.
+    pub const API: Self = Self {
+        run_timeout: 5_000,
+        compile_timeout: 15_000,
+        run_memory_limit: 256 * 1024 * 1024,
+        compile_memory_limit: 512 * 1024 * 1024,
+    };
+
+    /// Sets the limits of `request`, using the requested values where they stay within the
+    /// policy and the maximum otherwise.
+    pub fn apply(&self, request: ExecuteRequest, requested: &RequestedLimits) -> ExecuteRequest {
+        let clamp = |requested: Option<usize>, max: usize| requested.unwrap_or(max).min(max);
+
+        request
+            .run_timeout(clamp(requested.run_timeout, self.run_timeout))
+            .compile_timeout(clamp(requested.compile_timeout, self.compile_timeout))
+            .run_memory_limit(clamp(requested.run_memory_limit, self.run_memory_limit) as isize)
+            .compile_memory_limit(
+                clamp(requested.compile_memory_limit, self.compile_memory_limit) as isize,
+            )
+    }
+}
+
+/// Limits asked for by the caller, which are clamped by a [`LimitPolicy`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RequestedLimits {
+    pub run_timeout: Option<usize>,
+    pub compile_timeout: Option<usize>,
+    pub run_memory_limit: Option<usize>,
+    pub compile_memory_limit: Option<usize>,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::services::code::request;
+
+    use super::*;
+
+    #[test]
+    fn uses_maximum_by_default() {
+        let request =
+            LimitPolicy::DISCORD.apply(request("python", ""), &RequestedLimits::default());
+        let request = serde_json::to_value(request).unwrap();
+
+        assert_eq!(request["run_timeout"], json!(3_000));
+        assert_eq!(request["compile_timeout"], json!(10_000));
+        assert_eq!(request["run_memory_limit"], json!(128 * 1024 * 1024));
+        assert_eq!(request["compile_memory_limit"], json!(256 * 1024 * 1024));
+    }
+
+    #[test]
+    fn clamps_requested_limits() {
+        let requested = RequestedLimits {
+            run_timeout: Some(1_000),
+            compile_timeout: Some(60_000),
+            run_memory_limit: Some(usize::MAX),
+            compile_memory_limit: None,
+        };
+
+        let request = LimitPolicy::API.apply(request("python", ""), &requested);
+        let request = serde_json::to_value(request).unwrap();
+
+        assert_eq!(request["run_timeout"], json!(1_000));
+        assert_eq!(request["compile_timeout"], json!(15_000));
+        assert_eq!(request["run_memory_limit"], json!(256 * 1024 * 1024));
+        assert_eq!(request["compile_memory_limit"], json!(512 * 1024 * 1024));
+    }
+}
@@ -0,0 +1,128 @@
+//! Code execution shared by `/code`, "Run Code", the code modal and `POST /code`.
+
+use base64::prelude::*;
+
+use crate::{
+    error::Error,
+    models::api::code::{ExecuteFile, ExecuteRequest, ExecuteResponse, LanguagesResponse},
+};
+
+mod blocks;
+mod catalogue;
+#[cfg(test)]
+mod fake;
+mod limits;
+mod piston;
+mod render;
+
+pub trait CodeExecutor {
+    async fn execute(&self, request: &ExecuteRequest) -> Result<ExecuteResponse, Error>;
+    async fn languages(&self) -> Result<LanguagesResponse, Error>;
+}
+
+/// Builds a request running `file` with the latest version of `language`.
+pub fn request_file(language: impl Into<String>, file: ExecuteFile) -> ExecuteRequest {
+    ExecuteRequest::new()
+        .language(language)
+        .version("*")
+        .add_file(file)
+}
+
+/// Builds a request running a single unnamed file with the latest version of `language`.
+#[cfg(test)]
+pub fn request(language: impl Into<String>, code: impl Into<String>) -> ExecuteRequest {
+    request_file(language, ExecuteFile::new().content(code))
+}
+
+/// Turns the contents of an uploaded file into a named file, encoding it as base64 if it is
+/// not valid UTF-8.
+pub fn file_from_bytes(name: impl Into<String>, bytes: Vec<u8>) -> ExecuteFile {
+    let file = ExecuteFile::new().name(name);
+
+    match String::from_utf8(bytes) {
+        Ok(content) => file.content(content),
+        Err(error) => file
+            .content(BASE64_STANDARD.encode(error.into_bytes()))
+            .encoding("base64"),
+    }
+}
+
+/// Splits program arguments on whitespace, keeping text in double quotes together.
+pub fn parse_args(input: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut current: Option<String> = None;
+    let mut quoted = false;
+
+    for char in input.chars() {
+        match char {
+            '"' => {
+                quoted = !quoted;
+                current.get_or_insert_default();
+            }
+            char if char.is_whitespace() && !quoted => {
+                args.extend(current.take());
+            }
+            char => current.get_or_insert_default().push(char),
+        }
+    }
+
+    args.extend(current);
+    args
+}
+
+/// Joins program arguments so that [`parse_args`] returns them unchanged.
+pub fn format_args(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| {
+            if arg.is_empty() || arg.contains(char::is_whitespace) {
+                format!("\"{arg}\"")
+            } else {
+                arg.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub use blocks::extract_code_blocks;
+pub use catalogue::LanguageCatalogue;
+#[cfg(test)]
+pub use fake::FakeExecutor;
+pub use limits::{LimitPolicy, RequestedLimits};
+pub use piston::PistonExecutor;
+pub use render::{Stream, page_buttons, page_description, paginate, render_output};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_args() {
+        assert_eq!(parse_args(""), Vec::<String>::new());
+        assert_eq!(parse_args("  a  b\tc "), ["a", "b", "c"]);
+        assert_eq!(
+            parse_args(r#"-n "hello world" """#),
+            ["-n", "hello world", ""]
+        );
+    }
+
+    #[test]
+    fn formats_args() {
+        let args = ["-n", "hello world", ""].map(String::from);
+
+        assert_eq!(format_args(&args), r#"-n "hello world" """#);
+        assert_eq!(parse_args(&format_args(&args)), args);
+    }
+
+    #[test]
+    fn encodes_binary_files() {
+        let text = serde_json::to_value(file_from_bytes("a.txt", b"hi".to_vec())).unwrap();
+        assert_eq!(text["name"], "a.txt");
+        assert_eq!(text["content"], "hi");
+        assert!(text["encoding"].is_null());
+
+        let binary = serde_json::to_value(file_from_bytes("a.bin", vec![0xff, 0x00])).unwrap();
+        assert_eq!(binary["content"], "/wA=");
+        assert_eq!(binary["encoding"], "base64");
+    }
+}
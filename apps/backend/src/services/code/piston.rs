@@ -0,0 +1,58 @@
+use crate::{
+    error::Error,
+    models::api::code::{ExecuteRequest, ExecuteResponse, LanguagesResponse},
+};
+
+use super::CodeExecutor;
+
+/// Executes code on a remote service implementing the Piston API.
+pub struct PistonExecutor {
+    http: reqwest::Client,
+    base_url: String,
+    token: String,
+}
+
+impl PistonExecutor {
+    pub fn new(
+        http: reqwest::Client,
+        base_url: impl Into<String>,
+        token: impl Into<String>,
+    ) -> Self {
+        Self {
+            http,
+            base_url: base_url.into(),
+            token: token.into(),
+        }
+    }
+}
+
+impl CodeExecutor for PistonExecutor {
+    async fn execute(&self, request: &ExecuteRequest) -> Result<ExecuteResponse, Error> {
+        let response = self
+            .http
+            .post(format!("{}/execute", self.base_url))
+            .header("Authorization", &self.token)
+            .json(request)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<ExecuteResponse>()
+            .await?;
+
+        Ok(response)
+    }
+
+    async fn languages(&self) -> Result<LanguagesResponse, Error> {
+        let response = self
+            .http
+            .get(format!("{}/runtimes", self.base_url))
+            .header("Authorization", &self.token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<LanguagesResponse>()
+            .await?;
+
+        Ok(response)
+    }
+}
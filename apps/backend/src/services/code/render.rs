@@ -0,0 +1,387 @@
+use serenity::all::{
+    ButtonStyle, Color, CreateActionRow, CreateAttachment, CreateButton, CreateEmbed,
+    CreateEmbedFooter, CreateInteractionResponseFollowup,
+};
+
+use crate::models::{
+    api::code::{ExecuteResponse, ExecuteStage},
+    custom_id::CustomId,
+};
+
+/// Maximum length of a single page of output shown in the embed description.
+const PAGE_LENGTH: usize = 1500;
+
+/// Maximum length of stderr shown in an embed field next to stdout, fields are limited to 1024
+/// characters by Discord.
+const STDERR_PREVIEW_LENGTH: usize = 900;
+
+/// Output stream of a program, shown in pages and attached as a file if it is too long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Stdout => "stdout",
+            Self::Stderr => "stderr",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "stdout" => Some(Self::Stdout),
+            "stderr" => Some(Self::Stderr),
+            _ => None,
+        }
+    }
+
+    pub fn file_name(self) -> String {
+        format!("{}.txt", self.name())
+    }
+}
+
+/// Message parts for the result of an execution.
+pub struct RenderedOutput {
+    pub embed: CreateEmbed,
+    pub attachments: Vec<CreateAttachment>,
+    pub components: Vec<CreateActionRow>,
+}
+
+impl RenderedOutput {
+    pub fn followup(self) -> CreateInteractionResponseFollowup {
+        CreateInteractionResponseFollowup::new()
+            .add_embed(self.embed)
+            .add_files(self.attachments)
+            .components(self.components)
+    }
+}
+
+/// Renders the result of an execution, showing the compiler output instead if compilation
+/// failed.
+///
+/// The first page of stdout is shown in the description, with stderr in a separate field. If
+/// the program only wrote to stderr, stderr is paged instead. Streams that do not fit are
+/// attached as files, which the page buttons read the other pages from.
+pub fn render_output(response: &ExecuteResponse) -> RenderedOutput {
+    let compile_error = response
+        .compile
+        .as_ref()
+        .filter(|compile| compile.code.is_some_and(|code| code != 0));
+
+    let (title, color, stage) = match compile_error {
+        Some(compile) => ("Compile Error", Color::RED, compile),
+        None if exit_failed(&response.run) => ("Output", Color::ORANGE, &response.run),
+        None => ("Output", Color::FOOYOO, &response.run),
+    };
+
+    let (stream, text) = if stage.stdout.is_empty() && !stage.stderr.is_empty() {
+        (Stream::Stderr, &stage.stderr)
+    } else {
+        (Stream::Stdout, &stage.stdout)
+    };
+
+    let pages = paginate(text);
+
+    let mut embed = CreateEmbed::new()
+        .color(color)
+        .title(title)
+        .description(page_description(&pages, 0))
+        .footer(CreateEmbedFooter::new(format!(
+            "Language: {} {} • {}",
+            response.language,
+            response.version,
+            exit_status(stage)
+        )));
+
+    let mut attachments = vec![];
+    let mut components = vec![];
+
+    if pages.len() > 1 {
+        attachments.push(CreateAttachment::bytes(text.as_bytes(), stream.file_name()));
+        components.push(page_buttons(stream, 0, pages.len()));
+    }
+
+    if stream == Stream::Stdout && !stage.stderr.is_empty() {
+        let (preview, truncated) = truncate(&stage.stderr, STDERR_PREVIEW_LENGTH);
+
+        let mut field = format!("```\n{}\n```", preview.trim_end_matches('\n'));
+
+        if truncated {
+            field.push_str(&format!(
+                "\n-# Full output in {}",
+                Stream::Stderr.file_name()
+            ));
+            attachments.push(CreateAttachment::bytes(
+                stage.stderr.as_bytes(),
+                Stream::Stderr.file_name(),
+            ));
+        }
+
+        embed = embed.field("stderr", field, false);
+    }
+
+    RenderedOutput {
+        embed,
+        attachments,
+        components,
+    }
+}
+
+/// Splits `text` into pages of at most [`PAGE_LENGTH`] bytes, breaking at line ends where
+/// possible. Always returns at least one page.
+pub fn paginate(text: &str) -> Vec<String> {
+    let mut pages = vec![];
+    let mut rest = text;
+
+    while rest.len() > PAGE_LENGTH {
+        let mut end = PAGE_LENGTH;
+
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        if let Some(newline) = rest[..end].rfind('\n') {
+            end = newline + 1;
+        }
+
+        pages.push(rest[..end].to_string());
+        rest = &rest[end..];
+    }
+
+    if !rest.is_empty() || pages.is_empty() {
+        pages.push(rest.to_string());
+    }
+
+    pages
+}
+
+/// Formats page `page` of `pages` for an embed description.
+pub fn page_description(pages: &[String], page: usize) -> String {
+    let content = pages[page].trim_end_matches('\n');
+
+    if content.is_empty() {
+        return String::from("```\nNo output, try logging the expression.\n```");
+    }
+
+    let mut description = format!("```\n{content}\n```");
+
+    if pages.len() > 1 {
+        description.push_str(&format!("\n-# Page {}/{}", page + 1, pages.len()));
+    }
+
+    description
+}
+
+/// Previous and next buttons for page `page` of `stream`.
+pub fn page_buttons(stream: Stream, page: usize, page_count: usize) -> CreateActionRow {
+    let last = page_count.saturating_sub(1);
+
+    let button = |target: usize| {
+        CustomId::new("code_output")
+            .add_data(stream.name())
+            .add_data(target.to_string())
+            .try_to_string()
+            .unwrap()
+    };
+
+    CreateActionRow::Buttons(vec![
+        CreateButton::new(button(page.saturating_sub(1)))
+            .label("Prev")
+            .style(ButtonStyle::Secondary)
+            .disabled(page == 0),
+        CreateButton::new(button((page + 1).min(last)))
+            .label("Next")
+            .style(ButtonStyle::Secondary)
+            .disabled(page >= last),
+    ])
+}
+
+fn exit_failed(stage: &ExecuteStage) -> bool {
+    stage.signal.is_some() || stage.code.is_some_and(|code| code != 0)
+}
+
+fn exit_status(stage: &ExecuteStage) -> String {
+    match (&stage.signal, stage.code) {
+        (Some(signal), _) => format!("Killed by {signal}"),
+        (None, Some(code)) => format!("Exit code {code}"),
+        (None, None) => String::from("No exit code"),
+    }
+}
+
+/// Cuts `text` to at most `length` bytes, returning whether anything was cut.
+fn truncate(text: &str, length: usize) -> (&str, bool) {
+    if text.len() <= length {
+        return (text, false);
+    }
+
+    let mut end = length;
+
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    (&text[..end], true)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::services::code::{CodeExecutor, FakeExecutor, request};
+
+    use super::*;
+
+    async fn render(executor: &FakeExecutor) -> (Value, RenderedOutput) {
+        let response = executor
+            .execute(&request("python", "print(1)"))
+            .await
+            .unwrap();
+
+        let output = render_output(&response);
+
+        (serde_json::to_value(&output.embed).unwrap(), output)
+    }
+
+    fn stage(
+        stdout: &str,
+        stderr: &str,
+        code: Option<isize>,
+        signal: Option<&str>,
+    ) -> ExecuteStage {
+        ExecuteStage {
+            stdout: stdout.to_string(),
+            stderr: stderr.to_string(),
+            output: format!("{stdout}{stderr}"),
+            code,
+            signal: signal.map(String::from),
+        }
+    }
+
+    fn response(run: ExecuteStage, compile: Option<ExecuteStage>) -> ExecuteResponse {
+        ExecuteResponse {
+            language: String::from("rust"),
+            version: String::from("1.68.2"),
+            run,
+            compile,
+        }
+    }
+
+    #[tokio::test]
+    async fn renders_output() {
+        let executor = FakeExecutor::with_output("1\n");
+        let (embed, output) = render(&executor).await;
+
+        assert_eq!(embed["title"], "Output");
+        assert_eq!(embed["description"], "```\n1\n```");
+        assert_eq!(
+            embed["footer"]["text"],
+            "Language: python 3.12.0 • Exit code 0"
+        );
+        assert!(output.attachments.is_empty());
+        assert!(output.components.is_empty());
+
+        let requests = executor.requests.lock().unwrap();
+        assert_eq!(requests[0]["version"], "*");
+        assert_eq!(requests[0]["files"][0]["content"], "print(1)");
+    }
+
+    #[tokio::test]
+    async fn renders_empty_output() {
+        let (embed, _) = render(&FakeExecutor::with_output("")).await;
+
+        assert_eq!(
+            embed["description"],
+            "```\nNo output, try logging the expression.\n```"
+        );
+    }
+
+    #[tokio::test]
+    async fn renders_compile_error() {
+        let executor = FakeExecutor::with_response(response(
+            ExecuteStage::default(),
+            Some(stage("", "error[E0425]", Some(1), None)),
+        ));
+        let (embed, _) = render(&executor).await;
+
+        assert_eq!(embed["title"], "Compile Error");
+        assert_eq!(embed["description"], "```\nerror[E0425]\n```");
+        assert_eq!(
+            embed["footer"]["text"],
+            "Language: rust 1.68.2 • Exit code 1"
+        );
+    }
+
+    #[tokio::test]
+    async fn separates_stderr_and_reports_signal() {
+        let executor = FakeExecutor::with_response(response(
+            stage("partial\n", "oops\n", None, Some("SIGKILL")),
+            None,
+        ));
+        let (embed, _) = render(&executor).await;
+
+        assert_eq!(embed["description"], "```\npartial\n```");
+        assert_eq!(embed["fields"][0]["name"], "stderr");
+        assert_eq!(embed["fields"][0]["value"], "```\noops\n```");
+        assert!(
+            embed["footer"]["text"]
+                .as_str()
+                .unwrap()
+                .ends_with("Killed by SIGKILL")
+        );
+    }
+
+    #[tokio::test]
+    async fn attaches_long_output() {
+        let stdout = "line\n".repeat(1000);
+        let stderr = "e".repeat(STDERR_PREVIEW_LENGTH + 1);
+
+        let executor =
+            FakeExecutor::with_response(response(stage(&stdout, &stderr, Some(0), None), None));
+        let (embed, output) = render(&executor).await;
+
+        assert!(embed["description"].as_str().unwrap().ends_with("Page 1/4"));
+        assert_eq!(output.attachments.len(), 2);
+        assert_eq!(output.attachments[0].filename, "stdout.txt");
+        assert_eq!(output.attachments[1].filename, "stderr.txt");
+        assert_eq!(output.components.len(), 1);
+    }
+
+    #[test]
+    fn paginates_at_line_ends() {
+        assert_eq!(paginate(""), [""]);
+
+        let text = format!("{}\n{}", "a".repeat(PAGE_LENGTH - 10), "b".repeat(20));
+        let pages = paginate(&text);
+
+        assert_eq!(pages.len(), 2);
+        assert!(pages[0].ends_with("a\n"));
+        assert_eq!(pages[1], "b".repeat(20));
+        assert_eq!(pages.concat(), text);
+
+        let text = "ü".repeat(PAGE_LENGTH);
+        assert_eq!(paginate(&text).concat(), text);
+    }
+
+    #[test]
+    fn page_buttons_stay_in_range() {
+        let ids = |page| {
+            let row = serde_json::to_value(page_buttons(Stream::Stdout, page, 3)).unwrap();
+            (
+                row["components"][0]["custom_id"].clone(),
+                row["components"][1]["custom_id"].clone(),
+            )
+        };
+
+        assert_eq!(
+            ids(0),
+            ("code_output,stdout,0".into(), "code_output,stdout,1".into())
+        );
+        assert_eq!(
+            ids(2),
+            ("code_output,stdout,1".into(), "code_output,stdout,2".into())
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use numbat_exchange_rates::parse_exchange_rates;
+
+use crate::error::Error;
+
+/// ECB reference rates shipped with the bot, used if neither the source nor the cache is
+/// available.
+const BUNDLED_RATES: &str = include_str!("../../assets/exchange-rates.xml");
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where a set of exchange rates was loaded from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RatesOrigin {
+    Source,
+    Cache,
+    Bundled,
+}
+
+/// Exchange rates in the XML format of the European Central Bank.
+#[derive(Debug)]
+pub struct ExchangeRates {
+    pub xml: String,
+    pub origin: RatesOrigin,
+}
+
+/// Loads exchange rates from a remote source, keeping the last successful response on disk for
+/// when the source is unreachable.
+pub struct ExchangeRateProvider {
+    http: reqwest::Client,
+    url: String,
+    cache_path: PathBuf,
+}
+
+impl ExchangeRateProvider {
+    pub fn new(
+        http: reqwest::Client,
+        url: impl Into<String>,
+        cache_path: impl AsRef<Path>,
+    ) -> Self {
+        Self {
+            http,
+            url: url.into(),
+            cache_path: cache_path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Returns the current rates of the source, falling back to the cached rates and then to
+    /// the bundled rates.
+    pub async fn load(&self) -> ExchangeRates {
+        match self.fetch().await {
+            Ok(xml) => {
+                if let Err(error) = self.write_cache(&xml).await {
+                    tracing::warn!(
+                        %error,
+                        path = %self.cache_path.display(),
+                        "failed to cache exchange rates"
+                    );
+                }
+
+                return ExchangeRates {
+                    xml,
+                    origin: RatesOrigin::Source,
+                };
+            }
+            Err(error) => tracing::warn!(%error, url = self.url, "failed to fetch exchange rates"),
+        }
+
+        match tokio::fs::read_to_string(&self.cache_path).await {
+            Ok(xml) if is_valid(&xml) => {
+                return ExchangeRates {
+                    xml,
+                    origin: RatesOrigin::Cache,
+                };
+            }
+            Ok(_) => tracing::warn!(
+                path = %self.cache_path.display(),
+                "ignoring invalid cached exchange rates"
+            ),
+            Err(error) => tracing::debug!(%error, "no cached exchange rates"),
+        }
+
+        ExchangeRates {
+            xml: BUNDLED_RATES.to_string(),
+            origin: RatesOrigin::Bundled,
+        }
+    }
+
+    async fn fetch(&self) -> Result<String, Error> {
+        let xml = self
+            .http
+            .get(&self.url)
+            .timeout(FETCH_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        if !is_valid(&xml) {
+            return Err(anyhow!("Response does not contain any exchange rates"));
+        }
+
+        Ok(xml)
+    }
+
+    async fn write_cache(&self, xml: &str) -> Result<(), Error> {
+        if let Some(parent) = self.cache_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        tokio::fs::write(&self.cache_path, xml).await?;
+
+        Ok(())
+    }
+}
+
+fn is_valid(xml: &str) -> bool {
+    parse_exchange_rates(xml).is_some_and(|rates| !rates.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, routing::get};
+
+    use super::*;
+
+    /// Address that refuses connections, to simulate being offline.
+    const UNREACHABLE_URL: &str = "http://127.0.0.1:1/rates.xml";
+
+    fn cache_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir()
+            .join(format!("liege-exchange-rates-{}", std::process::id()))
+            .join(name);
+
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    async fn serve(body: &'static str) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let app = Router::new().route("/rates.xml", get(move || async move { body }));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        format!("http://{address}/rates.xml")
+    }
+
+    #[test]
+    fn bundled_rates_are_valid() {
+        let rates = parse_exchange_rates(BUNDLED_RATES).unwrap();
+        assert!(rates.contains_key("USD"));
+    }
+
+    #[tokio::test]
+    async fn caches_fetched_rates() {
+        let path = cache_path("fetched.xml");
+        let url = serve(BUNDLED_RATES).await;
+
+        let rates = ExchangeRateProvider::new(reqwest::Client::new(), url, &path)
+            .load()
+            .await;
+        assert_eq!(rates.origin, RatesOrigin::Source);
+
+        let rates = ExchangeRateProvider::new(reqwest::Client::new(), UNREACHABLE_URL, &path)
+            .load()
+            .await;
+        assert_eq!(rates.origin, RatesOrigin::Cache);
+        assert_eq!(rates.xml, BUNDLED_RATES);
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_bundled_rates() {
+        let path = cache_path("missing.xml");
+
+        let rates = ExchangeRateProvider::new(reqwest::Client::new(), UNREACHABLE_URL, &path)
+            .load()
+            .await;
+        assert_eq!(rates.origin, RatesOrigin::Bundled);
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_rates() {
+        let path = cache_path("invalid.xml");
+        let url = serve("<html>Service unavailable</html>").await;
+
+        let rates = ExchangeRateProvider::new(reqwest::Client::new(), url, &path)
+            .load()
+            .await;
+        assert_eq!(rates.origin, RatesOrigin::Bundled);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,3 @@
+pub mod ai;
+pub mod code;
+pub mod exchange_rates;
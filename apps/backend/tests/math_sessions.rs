@@ -0,0 +1,342 @@
+//! Evaluations in math sessions, which start the worker processes of the bot binary.
+
+use std::time::Duration;
+
+use liege_bot::math::{self, EvaluationError, EvaluationLimits, MathSessions, Modules};
+use serenity::all::UserId;
+
+const WORKER: &str = env!("CARGO_BIN_EXE_liege-bot");
+
+fn sessions() -> MathSessions {
+    MathSessions::new(
+        2,
+        Duration::from_secs(60),
+        EvaluationLimits::default(),
+        WORKER,
+    )
+}
+
+#[tokio::test]
+async fn keeps_definitions() {
+    let sessions = sessions();
+    let user_id = UserId::new(1);
+
+    sessions
+        .evaluate(user_id, "let x = 5 m", false)
+        .await
+        .unwrap();
+    let result = sessions.evaluate(user_id, "x * 2", false).await.unwrap();
+    assert!(result.starts_with("10 m"), "{result}");
+
+    assert!(sessions.evaluate(UserId::new(2), "x", false).await.is_err());
+}
+
+#[tokio::test]
+async fn lists_definitions() {
+    let sessions = sessions();
+    let user_id = UserId::new(1);
+
+    assert!(sessions.definitions(user_id).await.unwrap().is_empty());
+
+    sessions
+        .evaluate(user_id, "let x = 5 m", false)
+        .await
+        .unwrap();
+    sessions
+        .evaluate(user_id, "fn scaled(a) = 2 a", false)
+        .await
+        .unwrap();
+
+    let definitions = sessions.definitions(user_id).await.unwrap();
+    let names: Vec<_> = definitions.iter().map(|d| d.name.as_str()).collect();
+    assert_eq!(names, ["scaled", "x"]);
+    assert!(definitions[1].value.starts_with("5 m"));
+}
+
+#[tokio::test]
+async fn resets_sessions() {
+    let sessions = sessions();
+    let user_id = UserId::new(1);
+
+    sessions
+        .evaluate(user_id, "let x = 1", false)
+        .await
+        .unwrap();
+    assert!(sessions.reset(user_id));
+    assert!(!sessions.reset(user_id));
+    assert!(sessions.evaluate(user_id, "x", false).await.is_err());
+}
+
+#[tokio::test]
+async fn evicts_least_recently_used() {
+    let sessions = sessions();
+
+    for id in 1..=2 {
+        sessions
+            .evaluate(UserId::new(id), "let x = 1", false)
+            .await
+            .unwrap();
+    }
+
+    // Using the first session makes the second one the least recently used
+    sessions.evaluate(UserId::new(1), "x", false).await.unwrap();
+    sessions.evaluate(UserId::new(3), "1", false).await.unwrap();
+
+    assert!(sessions.evaluate(UserId::new(1), "x", false).await.is_ok());
+    assert!(sessions.evaluate(UserId::new(2), "x", false).await.is_err());
+}
+
+#[tokio::test]
+async fn expires_sessions() {
+    let sessions = MathSessions::new(2, Duration::ZERO, EvaluationLimits::default(), WORKER);
+    let user_id = UserId::new(1);
+
+    sessions
+        .evaluate(user_id, "let x = 1", false)
+        .await
+        .unwrap();
+    assert!(sessions.evaluate(user_id, "x", false).await.is_err());
+
+    // Idle sessions are also dropped without being used again
+    sessions
+        .evaluate(user_id, "let x = 1", false)
+        .await
+        .unwrap();
+    sessions.expire();
+    assert!(!sessions.reset(user_id));
+}
+
+#[tokio::test]
+async fn times_out() {
+    let sessions = MathSessions::new(
+        2,
+        Duration::from_secs(60),
+        EvaluationLimits {
+            timeout: Duration::from_secs(3),
+            ..Default::default()
+        },
+        WORKER,
+    );
+    let user_id = UserId::new(1);
+
+    sessions
+        .evaluate(
+            user_id,
+            "fn fib(n: Scalar) -> Scalar = if n < 2 then n else fib(n - 1) + fib(n - 2)",
+            false,
+        )
+        .await
+        .unwrap();
+
+    let result = sessions.evaluate(user_id, "fib(40)", false).await;
+    assert!(matches!(result, Err(EvaluationError::TimedOut(_))));
+
+    // The worker running the evaluation is killed with its session, so a fresh one is started
+    assert!(sessions.evaluate(user_id, "fib(2)", false).await.is_err());
+    assert!(sessions.evaluate(user_id, "1 + 1", false).await.is_ok());
+}
+
+#[tokio::test]
+async fn frees_workers_on_timeout() {
+    let sessions = MathSessions::new(
+        2,
+        Duration::from_secs(60),
+        EvaluationLimits {
+            timeout: Duration::from_secs(1),
+            max_workers: 1,
+            ..Default::default()
+        },
+        WORKER,
+    );
+
+    sessions
+        .evaluate(
+            UserId::new(1),
+            "fn fib(n: Scalar) -> Scalar = if n < 2 then n else fib(n - 1) + fib(n - 2)",
+            false,
+        )
+        .await
+        .unwrap();
+
+    let result = sessions.evaluate(UserId::new(1), "fib(40)", false).await;
+    assert!(matches!(result, Err(EvaluationError::TimedOut(_))));
+
+    // The only worker permit was released with the killed worker
+    assert!(
+        sessions
+            .evaluate(UserId::new(2), "1 + 1", false)
+            .await
+            .is_ok()
+    );
+}
+
+#[tokio::test]
+async fn limits_memory() {
+    let sessions = MathSessions::new(
+        2,
+        Duration::from_secs(60),
+        EvaluationLimits {
+            timeout: Duration::from_secs(30),
+            max_memory: 64 * 1024 * 1024,
+            ..Default::default()
+        },
+        WORKER,
+    );
+    let user_id = UserId::new(1);
+
+    sessions
+        .evaluate(user_id, "fn f(n: Scalar) -> Scalar = f(n + 1) + 1", false)
+        .await
+        .unwrap();
+
+    let result = sessions.evaluate(user_id, "f(0)", false).await;
+    assert!(
+        matches!(result, Err(EvaluationError::Crashed)),
+        "{result:?}"
+    );
+
+    assert!(sessions.evaluate(user_id, "1 + 1", false).await.is_ok());
+}
+
+#[tokio::test]
+async fn limits_input() {
+    let sessions = MathSessions::new(
+        2,
+        Duration::from_secs(60),
+        EvaluationLimits {
+            max_input_length: 10,
+            max_session_size: 16,
+            ..Default::default()
+        },
+        WORKER,
+    );
+    let user_id = UserId::new(1);
+
+    let result = sessions.evaluate(user_id, "1 + 1 + 1 + 1", false).await;
+    assert!(matches!(result, Err(EvaluationError::InputTooLong(10))));
+
+    sessions
+        .evaluate(user_id, "let x = 1", false)
+        .await
+        .unwrap();
+    let result = sessions.evaluate(user_id, "let y = 2", false).await;
+    assert!(matches!(result, Err(EvaluationError::SessionTooLarge)));
+
+    sessions.reset(user_id);
+    assert!(sessions.evaluate(user_id, "let y = 2", false).await.is_ok());
+}
+
+#[tokio::test]
+async fn imports_modules() {
+    let sessions = sessions();
+    let user_id = UserId::new(1);
+
+    let mut modules = Modules::default();
+    modules.add("team::constants", "<user>", "let answer = 42");
+
+    sessions
+        .with_modules(modules)
+        .evaluate(user_id, "use team::constants", false)
+        .await
+        .unwrap();
+
+    // Imported definitions stay in the session like other definitions
+    let result = sessions.evaluate(user_id, "answer", false).await.unwrap();
+    assert_eq!(result, "42");
+}
+
+#[tokio::test]
+async fn checks_modules() {
+    let sessions = sessions();
+    let user_id = UserId::new(1);
+
+    sessions
+        .evaluate(user_id, "let x = 1", false)
+        .await
+        .unwrap();
+
+    let mut modules = Modules::default();
+    modules.add("base", "<user>", "let y = 2");
+
+    let check = |code| sessions.with_modules(modules.clone()).check_module(code);
+
+    assert!(check("use base\nlet z = y + 1").await.is_ok());
+    assert!(check("let z = ").await.is_err());
+    // Modules can not use the definitions of the session
+    assert!(check("let z = x").await.is_err());
+}
+
+#[tokio::test]
+async fn limits_modules_apart_from_input() {
+    let sessions = MathSessions::new(
+        2,
+        Duration::from_secs(60),
+        EvaluationLimits {
+            max_input_length: 10,
+            ..Default::default()
+        },
+        WORKER,
+    );
+
+    let check = |code| sessions.with_modules(Modules::default()).check_module(code);
+
+    assert!(check("let answer = 42").await.is_ok());
+
+    let code = "1".repeat(math::MAX_CODE_LENGTH + 1);
+    let result = check(&code).await;
+    assert!(matches!(
+        result,
+        Err(EvaluationError::InputTooLong(math::MAX_CODE_LENGTH))
+    ));
+}
+
+#[tokio::test]
+async fn counts_imported_modules() {
+    let sessions = MathSessions::new(
+        2,
+        Duration::from_secs(60),
+        EvaluationLimits {
+            max_session_size: 64,
+            ..Default::default()
+        },
+        WORKER,
+    );
+    let user_id = UserId::new(1);
+
+    let mut modules = Modules::default();
+    modules.add("big", "<user>", &format!("let x = 1\n# {}", "a".repeat(60)));
+
+    sessions
+        .with_modules(modules)
+        .evaluate(user_id, "use big", false)
+        .await
+        .unwrap();
+
+    let result = sessions.evaluate(user_id, "x", false).await;
+    assert!(matches!(result, Err(EvaluationError::SessionTooLarge)));
+}
+
+#[tokio::test]
+async fn converts_currencies() {
+    math::install_exchange_rates(include_str!("../assets/exchange-rates.xml"));
+
+    let result = sessions()
+        .evaluate(UserId::new(1), "10 EUR -> USD", false)
+        .await
+        .unwrap();
+    assert!(result.starts_with("10.299 $"), "{result}");
+}
+
+#[tokio::test]
+async fn converts_units() {
+    let sessions = sessions();
+
+    let conversion = sessions.convert(1.0, "mile", "ft").await.unwrap();
+    assert!(conversion.result.starts_with("5280 ft"), "{conversion:?}");
+
+    let result = sessions.convert(1.0, "m", "s").await;
+    assert!(
+        matches!(result, Err(EvaluationError::Numbat(_))),
+        "{result:?}"
+    );
+}